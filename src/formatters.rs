@@ -0,0 +1,168 @@
+// formatters.rs
+
+use crate::logly::{hostname, LogLevel, LogRecord, RecordFormatter};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Syslog severity of a level (0 = emergency ... 7 = debug)
+fn syslog_severity(level: LogLevel) -> u8 {
+    match level {
+        LogLevel::Fatal => 0,
+        LogLevel::Critical => 2,
+        LogLevel::Error => 3,
+        LogLevel::Warn => 4,
+        LogLevel::Info => 6,
+        LogLevel::Debug => 7,
+        LogLevel::Trace => 7,
+    }
+}
+
+// Quote and escape a string as a JSON string literal
+fn json_string(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+// Formats records as GELF 1.1 JSON for Graylog
+pub struct GelfFormatter {
+    host: String,
+}
+
+impl GelfFormatter {
+    // Create a formatter reporting the system hostname
+    pub fn new() -> Self {
+        GelfFormatter { host: hostname() }
+    }
+
+    // Create a formatter reporting the given host
+    pub fn with_host(host: &str) -> Self {
+        GelfFormatter { host: host.to_string() }
+    }
+
+    // GELF additional field name: `_` prefixed, invalid characters replaced, and the reserved `_id` and the
+    // record's own `_key` avoided by a trailing `_`
+    fn additional_field(name: &str) -> String {
+        let name: String = name
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-') { c } else { '_' })
+            .collect();
+        if name == "id" || name == "key" {
+            format!("_{}_", name)
+        } else {
+            format!("_{}", name)
+        }
+    }
+}
+
+impl Default for GelfFormatter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RecordFormatter for GelfFormatter {
+    fn format(&self, record: &LogRecord) -> String {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+
+        let mut gelf = format!(
+            "{{\"version\":\"1.1\",\"host\":{},\"short_message\":{},\"timestamp\":{}.{:06},\"level\":{},\"_key\":{}",
+            json_string(&self.host),
+            json_string(&record.value),
+            now.as_secs(),
+            now.subsec_micros(),
+            syslog_severity(record.level),
+            json_string(record.key)
+        );
+        for (field_key, field_value) in &record.fields {
+            gelf.push_str(&format!(
+                ",{}:{}",
+                json_string(&Self::additional_field(field_key)),
+                json_string(field_value)
+            ));
+        }
+        gelf.push('}');
+        gelf
+    }
+}
+
+// CEF severity of a level (0 = lowest ... 10 = highest)
+fn cef_severity(level: LogLevel) -> u8 {
+    match level {
+        LogLevel::Trace => 0,
+        LogLevel::Debug => 1,
+        LogLevel::Info => 3,
+        LogLevel::Warn => 5,
+        LogLevel::Error => 7,
+        LogLevel::Critical => 9,
+        LogLevel::Fatal => 10,
+    }
+}
+
+// Escape a CEF header value (backslash and pipe)
+fn cef_header(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('|', "\\|").replace(['\r', '\n'], " ")
+}
+
+// Escape a CEF extension value (backslash, equals and line breaks)
+fn cef_extension(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('=', "\\=")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+// Formats records in the Common Event Format for SIEMs
+pub struct CefFormatter {
+    vendor: String,
+    product: String,
+    version: String,
+}
+
+impl CefFormatter {
+    // Create a formatter reporting the given device vendor, product and version
+    pub fn new(vendor: &str, product: &str, version: &str) -> Self {
+        CefFormatter {
+            vendor: vendor.to_string(),
+            product: product.to_string(),
+            version: version.to_string(),
+        }
+    }
+}
+
+impl RecordFormatter for CefFormatter {
+    // The key is the signature id, the value the event name and the fields the extension (fields whose key has
+    // no ASCII letter or digit are left out, as an extension key cannot be empty)
+    fn format(&self, record: &LogRecord) -> String {
+        let extension: Vec<String> = record
+            .fields
+            .iter()
+            .filter_map(|(field_key, field_value)| {
+                let field_key: String = field_key.chars().filter(|c| c.is_ascii_alphanumeric()).collect();
+                (!field_key.is_empty()).then(|| format!("{}={}", field_key, cef_extension(field_value)))
+            })
+            .collect();
+
+        format!(
+            "CEF:0|{}|{}|{}|{}|{}|{}|{}",
+            cef_header(&self.vendor),
+            cef_header(&self.product),
+            cef_header(&self.version),
+            cef_header(record.key),
+            cef_header(&record.value),
+            cef_severity(record.level),
+            extension.join(" ")
+        )
+    }
+}
@@ -0,0 +1,113 @@
+// global.rs
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::sync::OnceLock;
+
+use crate::logly::{LogColor, LogLevel, Logger};
+
+static GLOBAL_LOGGER: OnceLock<Logger> = OnceLock::new();
+
+// Error returned when logging through the global logger before set_global was called
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NoGlobalLogger;
+
+impl fmt::Display for NoGlobalLogger {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "no global logger set (call logly::set_global first)")
+    }
+}
+
+impl Error for NoGlobalLogger {}
+
+// Error returned by set_global when a global logger is already set
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GlobalLoggerAlreadySet;
+
+impl fmt::Display for GlobalLoggerAlreadySet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a global logger is already set")
+    }
+}
+
+impl Error for GlobalLoggerAlreadySet {}
+
+// Install the process-wide Logger used by the logly::info! style macros (it can only be set once)
+pub fn set_global(logger: Logger) -> Result<(), GlobalLoggerAlreadySet> {
+    GLOBAL_LOGGER.set(logger).map_err(|_| GlobalLoggerAlreadySet)
+}
+
+// Get the global Logger, if one was set
+pub fn global() -> Option<&'static Logger> {
+    GLOBAL_LOGGER.get()
+}
+
+// Log a message through the global Logger
+pub fn log(level: LogLevel, key: &str, value: &str, color: LogColor) -> Result<(), NoGlobalLogger> {
+    let logger = global().ok_or(NoGlobalLogger)?;
+    logger.log_with(level, key, value, &[], color);
+    Ok(())
+}
+
+// Flush the global Logger and the one installed with init_log_bridge; safe to call repeatedly or when none is set
+pub fn shutdown() -> io::Result<()> {
+    log::logger().flush();
+    match global() {
+        Some(logger) => logger.flush(),
+        None => Ok(()),
+    }
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __global_log {
+    ($level:ident, $key:expr, $value:expr $(,)?) => {
+        $crate::global::log(
+            $crate::logly::LogLevel::$level,
+            $key,
+            $value,
+            $crate::logly::LogLevel::$level.default_color(),
+        )
+    };
+    ($level:ident, $key:expr, $value:expr, $color:expr $(,)?) => {
+        $crate::global::log($crate::logly::LogLevel::$level, $key, $value, $color)
+    };
+}
+
+// Log through the global Logger: `logly::info!(key, value)` uses the level's default color,
+// `logly::info!(key, value, color)` a given one. Each returns Err(NoGlobalLogger) before set_global.
+#[macro_export]
+macro_rules! info {
+    ($($arg:tt)+) => { $crate::__global_log!(Info, $($arg)+) };
+}
+
+#[macro_export]
+macro_rules! warn {
+    ($($arg:tt)+) => { $crate::__global_log!(Warn, $($arg)+) };
+}
+
+#[macro_export]
+macro_rules! error {
+    ($($arg:tt)+) => { $crate::__global_log!(Error, $($arg)+) };
+}
+
+#[macro_export]
+macro_rules! debug {
+    ($($arg:tt)+) => { $crate::__global_log!(Debug, $($arg)+) };
+}
+
+#[macro_export]
+macro_rules! critical {
+    ($($arg:tt)+) => { $crate::__global_log!(Critical, $($arg)+) };
+}
+
+#[macro_export]
+macro_rules! fatal {
+    ($($arg:tt)+) => { $crate::__global_log!(Fatal, $($arg)+) };
+}
+
+#[macro_export]
+macro_rules! trace {
+    ($($arg:tt)+) => { $crate::__global_log!(Trace, $($arg)+) };
+}
@@ -1,4 +1,16 @@
 // lib.rs
 
-mod logly;
+pub mod logly;
+pub mod log_bridge;
+pub mod formatters;
+pub mod global;
+mod sampled;
 
+pub use global::{global, set_global, shutdown};
+pub use log_bridge::init_log_bridge;
+
+
+// Create a new Logger instance (shorthand for `logly::Logger::new()`)
+pub fn new() -> logly::Logger {
+    logly::Logger::new()
+}
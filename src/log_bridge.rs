@@ -0,0 +1,65 @@
+// log_bridge.rs
+
+use crate::logly::{LogLevel, Logger};
+
+// Forwards records logged through the `log` crate macros to a Logger
+pub struct LogBridge {
+    logger: &'static Logger,
+}
+
+impl LogBridge {
+    pub fn new(logger: &'static Logger) -> Self {
+        LogBridge { logger }
+    }
+}
+
+// Map a `log` level to the logly level with the same meaning (Critical and Fatal have no `log` equivalent)
+pub fn level_from_log(level: log::Level) -> LogLevel {
+    match level {
+        log::Level::Error => LogLevel::Error,
+        log::Level::Warn => LogLevel::Warn,
+        log::Level::Info => LogLevel::Info,
+        log::Level::Debug => LogLevel::Debug,
+        log::Level::Trace => LogLevel::Trace,
+    }
+}
+
+// Map the most verbose logly level let through to a `log` level filter (`log` has nothing more severe than Error)
+fn level_filter(level: Option<LogLevel>) -> log::LevelFilter {
+    match level {
+        Some(LogLevel::Trace) => log::LevelFilter::Trace,
+        Some(LogLevel::Debug) => log::LevelFilter::Debug,
+        Some(LogLevel::Info) => log::LevelFilter::Info,
+        Some(LogLevel::Warn) => log::LevelFilter::Warn,
+        Some(LogLevel::Error) => log::LevelFilter::Error,
+        Some(LogLevel::Critical | LogLevel::Fatal) | None => log::LevelFilter::Off,
+    }
+}
+
+impl log::Log for LogBridge {
+    // The record target is checked against the Logger's key mutes and filter directives
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        self.logger.enabled(metadata.target(), level_from_log(metadata.level()))
+    }
+
+    // The record target (the module path by default) becomes the key
+    fn log(&self, record: &log::Record) {
+        let level = level_from_log(record.level());
+        let message = record.args().to_string();
+        self.logger
+            .log_with(level, record.target(), &message, &[], level.default_color());
+    }
+
+    fn flush(&self) {
+        let _ = self.logger.flush();
+    }
+}
+
+// Install the Logger as the `log` crate's logger, so `log::info!` and friends are written by logly.
+// It is borrowed for the rest of the program, e.g. the global logger: init_log_bridge(logly::global().unwrap()).
+// The `log` max level follows the Logger's filter directives as set at this point.
+pub fn init_log_bridge(logger: &'static Logger) -> Result<(), log::SetLoggerError> {
+    log::set_logger(Box::leak(Box::new(LogBridge::new(logger))))?;
+    log::set_max_level(level_filter(logger.max_enabled_level()));
+    Ok(())
+}
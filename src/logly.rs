@@ -1,155 +1,2183 @@
-use std::fs;
-use std::io::Write;
-use std::path::{ PathBuf};
-use std::sync::Mutex;
-use std::fmt;
-
-// Define log levels
-#[derive(Debug, PartialEq, Clone, Copy)]
-pub enum LogLevel {
-    Info,
-    Warn,
-    Error,
-    Debug,
-    Critical,
-    Fatal,
-    Trace,
-}
-
-// Implement the Display trait for LogLevel
-impl fmt::Display for LogLevel {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            LogLevel::Info => write!(f, "Info"),
-            LogLevel::Warn => write!(f, "Warn"),
-            LogLevel::Error => write!(f, "Error"),
-            LogLevel::Debug => write!(f, "Debug"),
-            LogLevel::Critical => write!(f, "Critical"),
-            LogLevel::Fatal => write!(f, "Fatal"),
-            LogLevel::Trace => write!(f, "Trace"),
-        }
-    }
-}
-
-// Define log colors
-#[derive(Debug, PartialEq, Clone, Copy)]
-pub enum LogColor {
-    Red,
-    Yellow,
-    Cyan,
-    Blue,
-    White,
-    Critical,
-}
-
-// Struct to represent the logger
-pub struct Logger {
-    file: Mutex<Option<fs::File>>,
-    color_enabled: bool,
-    default_file_path: Option<PathBuf>,
-    default_max_file_size: u64,
-}
-
-impl Logger {
-    // Create a new Logger instance
-    pub fn new() -> Self {
-        Logger {
-            file: Mutex::new(None),
-            color_enabled: true,
-            default_file_path: None,
-            default_max_file_size: 100,
-        }
-    }
-
-    // Start logging (open the log file)
-    pub fn start_logging(&self, file_path: &str) -> std::io::Result<()> {
-        let file = fs::File::create(file_path)?;
-        *self.file.lock().unwrap() = Some(file);
-        Ok(())
-    }
-
-    // Stop logging (close the log file)
-    pub fn stop_logging(&self) {
-        *self.file.lock().unwrap() = None;
-    }
-
-    // Set default file path and max file size
-    pub fn set_default_file_path(&mut self, path: &str) {
-        self.default_file_path = Some(PathBuf::from(path));
-    }
-
-    pub fn set_default_max_file_size(&mut self, max_size: u64) {
-        self.default_max_file_size = max_size;
-    }
-
-    // Log a message with a specified level and color
-    fn log_message(&self, level: LogLevel, key: &str, value: &str, color: LogColor) {
-        let color_code = if self.color_enabled {
-            match color {
-                LogColor::Red => "\x1b[31m",
-                LogColor::Yellow => "\x1b[33m",
-                LogColor::Cyan => "\x1b[36m",
-                LogColor::Blue => "\x1b[34m",
-                LogColor::White => "\x1b[37m",
-                LogColor::Critical => "\x1b[1;31m",
-            }
-        } else {
-            ""
-        };
-
-        let reset_color = if self.color_enabled { "\x1b[0m" } else { "" };
-
-        let log_message = format!(
-            "{}[{}]: {} - {}{}{}\n",
-            color_code, level, key, value, reset_color, reset_color
-        );
-
-        print!("{}", log_message);
-
-        // Write to the log file if it's open
-        if let Some(ref mut file) = *self.file.lock().unwrap() {
-            if let Err(err) = file.write_all(log_message.as_bytes()) {
-                eprintln!("Error writing to log file: {}", err);
-            }
-        }
-    }
-
-    // Log methods for various levels and colors
-    pub fn info(&self, key: &str, value: &str, color: LogColor) {
-        self.log_message(LogLevel::Info, key, value, color);
-    }
-
-    pub fn warn(&self, key: &str, value: &str, color: LogColor) {
-        self.log_message(LogLevel::Warn, key, value, color);
-    }
-
-    pub fn error(&self, key: &str, value: &str, color: LogColor) {
-        self.log_message(LogLevel::Error, key, value, color);
-    }
-
-    pub fn debug(&self, key: &str, value: &str, color: LogColor) {
-        self.log_message(LogLevel::Debug, key, value, color);
-    }
-
-    pub fn critical(&self, key: &str, value: &str, color: LogColor) {
-        self.log_message(LogLevel::Critical, key, value, color);
-    }
-
-    pub fn fatal(&self, key: &str, value: &str, color: LogColor) {
-        self.log_message(LogLevel::Fatal, key, value, color);
-    }
-
-    pub fn trace(&self, key: &str, value: &str, color: LogColor) {
-        self.log_message(LogLevel::Trace, key, value, color);
-    }
-
-    pub fn log(&self, key: &str, value: &str, color: LogColor) {
-        self.log_message(LogLevel::Info, key, value, color);
-    }
-
-    // Set color enabled or disabled
-    pub fn set_color_enabled(&mut self, color_enabled: bool) {
-        self.color_enabled = color_enabled;
-    }
-}
-
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::{ PathBuf};
+use std::sync::{Mutex, MutexGuard, OnceLock, TryLockError};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use regex::Regex;
+
+// Define log levels
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum LogLevel {
+    Info,
+    Warn,
+    Error,
+    Debug,
+    Critical,
+    Fatal,
+    Trace,
+}
+
+impl LogLevel {
+    // Default color of the level, used where no color is passed (see the README)
+    pub fn default_color(&self) -> LogColor {
+        match self {
+            LogLevel::Info => LogColor::Cyan,
+            LogLevel::Warn => LogColor::Yellow,
+            LogLevel::Error => LogColor::Red,
+            LogLevel::Debug => LogColor::Blue,
+            LogLevel::Critical => LogColor::Critical,
+            LogLevel::Fatal => LogColor::Critical,
+            LogLevel::Trace => LogColor::Blue,
+        }
+    }
+
+    // Single-letter form used by compact output
+    pub fn short_name(&self) -> char {
+        match self {
+            LogLevel::Info => 'I',
+            LogLevel::Warn => 'W',
+            LogLevel::Error => 'E',
+            LogLevel::Debug => 'D',
+            LogLevel::Critical => 'C',
+            LogLevel::Fatal => 'F',
+            LogLevel::Trace => 'T',
+        }
+    }
+
+    // All log levels, in declaration order
+    pub const ALL: [LogLevel; 7] = [
+        LogLevel::Info,
+        LogLevel::Warn,
+        LogLevel::Error,
+        LogLevel::Debug,
+        LogLevel::Critical,
+        LogLevel::Fatal,
+        LogLevel::Trace,
+    ];
+}
+
+// Implement the Display trait for LogLevel
+impl fmt::Display for LogLevel {
+    // Padded, so {:<8} works for aligned columns
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            LogLevel::Info => "Info",
+            LogLevel::Warn => "Warn",
+            LogLevel::Error => "Error",
+            LogLevel::Debug => "Debug",
+            LogLevel::Critical => "Critical",
+            LogLevel::Fatal => "Fatal",
+            LogLevel::Trace => "Trace",
+        })
+    }
+}
+
+// Error returned when a level name can't be parsed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseLevelError(String);
+
+impl fmt::Display for ParseLevelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid level '{}': expected info, warn, error, debug, critical, fatal or trace", self.0)
+    }
+}
+
+impl std::error::Error for ParseLevelError {}
+
+impl LogLevel {
+    // Rank from least (Trace) to most severe (Fatal), used by filter directives
+    fn severity(&self) -> u8 {
+        match self {
+            LogLevel::Trace => 0,
+            LogLevel::Debug => 1,
+            LogLevel::Info => 2,
+            LogLevel::Warn => 3,
+            LogLevel::Error => 4,
+            LogLevel::Critical => 5,
+            LogLevel::Fatal => 6,
+        }
+    }
+}
+
+// Parse a level name in any case ("Info", "INFO"), the inverse of Display; "warning" is accepted for Warn
+impl FromStr for LogLevel {
+    type Err = ParseLevelError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "info" => Ok(LogLevel::Info),
+            "warn" | "warning" => Ok(LogLevel::Warn),
+            "error" => Ok(LogLevel::Error),
+            "debug" => Ok(LogLevel::Debug),
+            "critical" => Ok(LogLevel::Critical),
+            "fatal" => Ok(LogLevel::Fatal),
+            "trace" => Ok(LogLevel::Trace),
+            _ => Err(ParseLevelError(name.to_string())),
+        }
+    }
+}
+
+// Define log colors
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum LogColor {
+    Red,
+    Yellow,
+    Cyan,
+    Blue,
+    White,
+    Critical,
+    // Color from the 256-color palette
+    Ansi256(u8),
+    // 24-bit truecolor
+    Rgb(u8, u8, u8),
+}
+
+impl LogColor {
+    // SGR parameters selecting this color as foreground or background
+    fn sgr_params(&self, background: bool) -> Cow<'static, str> {
+        let params = match (self, background) {
+            (LogColor::Red, false) => "31",
+            (LogColor::Yellow, false) => "33",
+            (LogColor::Cyan, false) => "36",
+            (LogColor::Blue, false) => "34",
+            (LogColor::White, false) => "37",
+            (LogColor::Critical, false) => "1;31",
+            (LogColor::Red, true) => "41",
+            (LogColor::Yellow, true) => "43",
+            (LogColor::Cyan, true) => "46",
+            (LogColor::Blue, true) => "44",
+            (LogColor::White, true) => "47",
+            (LogColor::Critical, true) => "101",
+            (LogColor::Ansi256(index), _) => {
+                return Cow::Owned(format!("{};5;{}", if background { 48 } else { 38 }, index));
+            }
+            (LogColor::Rgb(r, g, b), _) => {
+                return Cow::Owned(format!("{};2;{};{};{}", if background { 48 } else { 38 }, r, g, b));
+            }
+        };
+        Cow::Borrowed(params)
+    }
+
+    // ANSI escape sequence selecting this color
+    fn ansi_code(&self) -> String {
+        format!("\x1b[{}m", self.sgr_params(false))
+    }
+}
+
+// Text style for a level, composed into one escape sequence with the color
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct LevelStyle {
+    // Foreground color, overriding the color passed to the log call
+    pub color: Option<LogColor>,
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub background: Option<LogColor>,
+}
+
+impl LevelStyle {
+    // ANSI escape sequence for this style, falling back to the given color
+    fn ansi_code(&self, color: LogColor) -> String {
+        let mut params = Vec::new();
+        if self.bold {
+            params.push(Cow::Borrowed("1"));
+        }
+        if self.italic {
+            params.push(Cow::Borrowed("3"));
+        }
+        if self.underline {
+            params.push(Cow::Borrowed("4"));
+        }
+        params.push(self.color.unwrap_or(color).sgr_params(false));
+        if let Some(background) = self.background {
+            params.push(background.sgr_params(true));
+        }
+
+        format!("\x1b[{}m", params.join(";"))
+    }
+}
+
+impl From<LogColor> for LevelStyle {
+    fn from(color: LogColor) -> Self {
+        LevelStyle {
+            color: Some(color),
+            ..LevelStyle::default()
+        }
+    }
+}
+
+// Error returned when a color spec can't be parsed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseColorError(String);
+
+impl fmt::Display for ParseColorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid color '{}': expected a color name, #rrggbb, rgb(r,g,b) or a 0-255 palette index", self.0)
+    }
+}
+
+impl std::error::Error for ParseColorError {}
+
+// Parse a number of 0 to 255 written with decimal digits only (no sign)
+fn decimal_u8(text: &str) -> Option<u8> {
+    if text.is_empty() || !text.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    text.parse().ok()
+}
+
+// Parse a color name ("cyan"), hex ("#ff8800"), rgb ("rgb(255,136,0)") or 256-color palette index ("208")
+impl FromStr for LogColor {
+    type Err = ParseColorError;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let error = || ParseColorError(spec.to_string());
+        let trimmed = spec.trim();
+
+        if let Some(hex) = trimmed.strip_prefix('#') {
+            // from_str_radix would also take a sign, so only hex digits are let through
+            if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(error());
+            }
+            let channel = |range| u8::from_str_radix(&hex[range], 16).map_err(|_| error());
+            return Ok(LogColor::Rgb(channel(0..2)?, channel(2..4)?, channel(4..6)?));
+        }
+
+        if let Some(channels) = trimmed.strip_prefix("rgb(").and_then(|rest| rest.strip_suffix(')')) {
+            let channels = channels
+                .split(',')
+                .map(|channel| decimal_u8(channel.trim()).ok_or_else(error))
+                .collect::<Result<Vec<u8>, _>>()?;
+            return match channels[..] {
+                [r, g, b] => Ok(LogColor::Rgb(r, g, b)),
+                _ => Err(error()),
+            };
+        }
+
+        if let Some(index) = decimal_u8(trimmed) {
+            return Ok(LogColor::Ansi256(index));
+        }
+
+        match trimmed.to_ascii_lowercase().as_str() {
+            "red" => Ok(LogColor::Red),
+            "yellow" => Ok(LogColor::Yellow),
+            "cyan" => Ok(LogColor::Cyan),
+            "blue" => Ok(LogColor::Blue),
+            "white" => Ok(LogColor::White),
+            "critical" | "bright red" => Ok(LogColor::Critical),
+            _ => Err(error()),
+        }
+    }
+}
+
+// A message as handed to custom sinks, after redaction and truncation
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogRecord<'a> {
+    pub level: LogLevel,
+    pub key: &'a str,
+    pub value: Cow<'a, str>,
+    // One-shot fields followed by context fields
+    pub fields: Vec<(Cow<'a, str>, Cow<'a, str>)>,
+    pub color: LogColor,
+}
+
+// Destination for log records besides the console and the log file (a network service, a test buffer, ...).
+// write runs under the Logger's write lock: a message it logs to the same Logger goes to stderr instead
+pub trait LogSink: Send + Sync {
+    fn write(&self, record: &LogRecord) -> io::Result<()>;
+
+    fn flush(&self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// Decides a new level for a record, or None to keep its level
+pub type LevelOverride = Box<dyn Fn(&LogRecord) -> Option<LogLevel> + Send + Sync>;
+
+// Least severe level a filter directive lets through, or Off for none
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LevelThreshold {
+    Off,
+    AtLeast(LogLevel),
+}
+
+impl LevelThreshold {
+    fn parse(name: &str) -> Result<Self, ParseLevelError> {
+        if name.trim().eq_ignore_ascii_case("off") {
+            return Ok(LevelThreshold::Off);
+        }
+        name.parse().map(LevelThreshold::AtLeast)
+    }
+
+    fn allows(&self, level: LogLevel) -> bool {
+        match self {
+            LevelThreshold::Off => false,
+            LevelThreshold::AtLeast(threshold) => level.severity() >= threshold.severity(),
+        }
+    }
+}
+
+// Parsed RUST_LOG style directives: module thresholds (longest first) and the bare default
+#[derive(Debug, Default, Clone, PartialEq)]
+struct FilterDirectives {
+    modules: Vec<(String, LevelThreshold)>,
+    default: Option<LevelThreshold>,
+}
+
+impl FilterDirectives {
+    fn parse(directives: &str) -> Result<Self, ParseLevelError> {
+        let mut parsed = FilterDirectives::default();
+        for directive in directives.split(',').map(str::trim).filter(|directive| !directive.is_empty()) {
+            let (module, threshold) = match directive.split_once('=') {
+                Some((module, level)) => (module.trim(), LevelThreshold::parse(level)?),
+                // A bare word is the default level, or a module with every level on (as in env_logger)
+                None => match LevelThreshold::parse(directive) {
+                    Ok(threshold) => {
+                        parsed.default = Some(threshold);
+                        continue;
+                    }
+                    Err(_) => (directive, LevelThreshold::AtLeast(LogLevel::Trace)),
+                },
+            };
+            // A module given again replaces its earlier directive, as in RUST_LOG
+            parsed.modules.retain(|(existing, _)| existing != module);
+            parsed.modules.push((module.to_string(), threshold));
+        }
+        parsed.modules.sort_by_key(|(module, _)| std::cmp::Reverse(module.len()));
+        Ok(parsed)
+    }
+
+    // Least severe level any key can pass with; keys matching no module pass everything unless a default is set
+    fn most_verbose(&self) -> Option<LogLevel> {
+        let default = self.default.unwrap_or(LevelThreshold::AtLeast(LogLevel::Trace));
+        self.modules
+            .iter()
+            .map(|(_, threshold)| *threshold)
+            .chain(std::iter::once(default))
+            .filter_map(|threshold| match threshold {
+                LevelThreshold::Off => None,
+                LevelThreshold::AtLeast(level) => Some(level),
+            })
+            .min_by_key(LogLevel::severity)
+    }
+
+    // The most specific module matching the key decides, then the default; anything else passes
+    fn allows(&self, key: &str, level: LogLevel) -> bool {
+        let module = self.modules.iter().find(|(module, _)| key_in_module(key, module));
+        match module.map(|(_, threshold)| threshold).or(self.default.as_ref()) {
+            Some(threshold) => threshold.allows(level),
+            None => true,
+        }
+    }
+}
+
+// Decides whether a record is logged at all
+pub type RecordFilter = Box<dyn Fn(&LogRecord) -> bool + Send + Sync>;
+
+// Fully custom rendering of a record, used instead of the built-in layout (the line ending is added by the logger).
+// It runs before the write lock is taken, so it may log through the same Logger
+pub trait RecordFormatter: Send + Sync {
+    fn format(&self, record: &LogRecord) -> String;
+}
+
+// Adds context fields (trace ids, request ids, ...) to every record before it is formatted
+pub trait ContextProvider: Send + Sync {
+    fn enrich(&self, fields: &mut Vec<(String, String)>);
+}
+
+thread_local! {
+    static TRACE_CONTEXT: RefCell<Option<(String, String)>> = const { RefCell::new(None) };
+    // Addresses of the Loggers this thread is writing to the outputs of, innermost last
+    static EMITTING: RefCell<Vec<usize>> = const { RefCell::new(Vec::new()) };
+    // Number of logging calls in progress on this thread
+    static LOGGING_DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+// Marks the current thread as inside a logging call until dropped, so the panic hook does not log into itself
+struct LoggingGuard;
+
+impl LoggingGuard {
+    fn enter() -> Self {
+        LOGGING_DEPTH.with(|depth| depth.set(depth.get() + 1));
+        LoggingGuard
+    }
+
+    fn is_logging() -> bool {
+        LOGGING_DEPTH.try_with(|depth| depth.get() > 0).unwrap_or(true)
+    }
+}
+
+impl Drop for LoggingGuard {
+    fn drop(&mut self) {
+        let _ = LOGGING_DEPTH.try_with(|depth| depth.set(depth.get() - 1));
+    }
+}
+
+// Marks the current thread as writing to a Logger's outputs until dropped (also when a sink panics)
+struct EmitGuard;
+
+impl EmitGuard {
+    fn enter(logger: &Logger) -> Self {
+        EMITTING.with(|emitting| emitting.borrow_mut().push(logger as *const Logger as usize));
+        EmitGuard
+    }
+
+    fn is_emitting(logger: &Logger) -> bool {
+        let address = logger as *const Logger as usize;
+        EMITTING
+            .try_with(|emitting| emitting.borrow().contains(&address))
+            .unwrap_or(false)
+    }
+}
+
+impl Drop for EmitGuard {
+    fn drop(&mut self) {
+        let _ = EMITTING.try_with(|emitting| emitting.borrow_mut().pop());
+    }
+}
+
+// Context provider adding the trace_id and span_id set with Logger::set_trace_context on the current thread
+pub struct TraceContext;
+
+impl ContextProvider for TraceContext {
+    fn enrich(&self, fields: &mut Vec<(String, String)>) {
+        TRACE_CONTEXT.with(|context| {
+            if let Some((trace_id, span_id)) = &*context.borrow() {
+                fields.push(("trace_id".to_string(), trace_id.clone()));
+                fields.push(("span_id".to_string(), span_id.clone()));
+            }
+        });
+    }
+}
+
+// Stream the console output is written to
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ConsoleTarget {
+    #[default]
+    Stdout,
+    Stderr,
+}
+
+// What the console color decision depends on, detected once when the Logger is created
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ColorEnvironment {
+    // NO_COLOR is set to a non-empty value
+    pub no_color: bool,
+    // CLICOLOR_FORCE is set to a non-empty value other than 0
+    pub clicolor_force: bool,
+    pub stdout_is_terminal: bool,
+    pub stderr_is_terminal: bool,
+}
+
+impl ColorEnvironment {
+    // Read the environment variables and check whether stdout and stderr are terminals
+    pub fn detect() -> Self {
+        ColorEnvironment {
+            no_color: std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()),
+            clicolor_force: std::env::var_os("CLICOLOR_FORCE").is_some_and(|value| !value.is_empty() && value != "0"),
+            stdout_is_terminal: io::stdout().is_terminal(),
+            stderr_is_terminal: io::stderr().is_terminal(),
+        }
+    }
+
+    fn is_terminal(&self, target: ConsoleTarget) -> bool {
+        match target {
+            ConsoleTarget::Stdout => self.stdout_is_terminal,
+            ConsoleTarget::Stderr => self.stderr_is_terminal,
+        }
+    }
+}
+
+// How embedded newlines in values are written, so one record stays one line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MultilinePolicy {
+    // Write line breaks as the two characters \n (and \r)
+    Escape,
+    // Keep line breaks but indent the continuation lines
+    Indent,
+    // Write line breaks unchanged
+    Raw,
+}
+
+impl MultilinePolicy {
+    fn apply<'a>(&self, text: Cow<'a, str>) -> Cow<'a, str> {
+        if !text.contains(['\n', '\r']) {
+            return text;
+        }
+
+        match self {
+            MultilinePolicy::Escape => Cow::Owned(text.replace('\r', "\\r").replace('\n', "\\n")),
+            MultilinePolicy::Indent => Cow::Owned(text.replace('\n', "\n    ")),
+            MultilinePolicy::Raw => text,
+        }
+    }
+}
+
+// Terminator written after each line
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    CrLf,
+    // CrLf on Windows, Lf elsewhere
+    Platform,
+}
+
+impl LineEnding {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+            LineEnding::Platform if cfg!(windows) => "\r\n",
+            LineEnding::Platform => "\n",
+        }
+    }
+}
+
+// Per-destination rendering options
+#[derive(Clone, Copy, PartialEq)]
+struct LineOptions {
+    colored: bool,
+    multiline: MultilinePolicy,
+    line_ending: LineEnding,
+}
+
+impl LineOptions {
+    // Whether both options give the same line for the record; the multiline policy only matters for line breaks
+    fn renders_like(&self, other: &LineOptions, record: &LogRecord) -> bool {
+        let line_breaks = record.value.contains(['\n', '\r'])
+            || record.fields.iter().any(|(_, field_value)| field_value.contains(['\n', '\r']));
+        self.colored == other.colored
+            && self.line_ending == other.line_ending
+            && (self.multiline == other.multiline || !line_breaks)
+    }
+}
+
+// What to do with a record that could not be written to the log file
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum FileErrorAction {
+    // Report the error on stderr and count it in the stats
+    #[default]
+    DropWithCount,
+    // Also write the record itself to stderr so it isn't lost
+    Fallback,
+}
+
+// Snapshot of the logger's runtime counters
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LoggerStats {
+    pub records_by_level: HashMap<LogLevel, u64>,
+    pub total_records: u64,
+    pub write_errors: u64,
+    pub fallback_records: u64,
+    pub filtered_records: u64,
+}
+
+impl LoggerStats {
+    // Number of records logged at the given level
+    pub fn records(&self, level: LogLevel) -> u64 {
+        self.records_by_level.get(&level).copied().unwrap_or(0)
+    }
+}
+
+// Snapshot of the logger's plain settings (sinks, providers, the formatter, level override, global filter,
+// filter directives, color environment and redaction rules are not included)
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoggerConfig {
+    pub log_file: Option<PathBuf>,
+    pub color_enabled: bool,
+    pub force_color: bool,
+    pub level_styles: HashMap<LogLevel, LevelStyle>,
+    pub field_key_color: Option<LogColor>,
+    pub field_value_color: Option<LogColor>,
+    pub compact: bool,
+    pub pad_level: bool,
+    pub console_enabled: bool,
+    pub file_enabled: bool,
+    pub console_target: ConsoleTarget,
+    pub level_console_targets: HashMap<LogLevel, ConsoleTarget>,
+    pub console_levels: HashMap<LogLevel, bool>,
+    pub storage_levels: HashMap<LogLevel, bool>,
+    pub multiline_policy: MultilinePolicy,
+    pub console_multiline_policy: MultilinePolicy,
+    pub line_ending: LineEnding,
+    pub console_line_ending: LineEnding,
+    pub max_message_len: Option<usize>,
+    pub interpolate_message: bool,
+    pub field_filters: Vec<(String, String)>,
+    pub muted_keys: Vec<String>,
+    pub include_host: bool,
+    pub include_pid: bool,
+    pub file_error_action: FileErrorAction,
+    pub file_mode: Option<u32>,
+    pub default_file_path: Option<PathBuf>,
+    pub default_max_file_size: u64,
+}
+
+// Counters updated on every log call
+#[derive(Default)]
+struct Stats {
+    records: [AtomicU64; LogLevel::ALL.len()],
+    write_errors: AtomicU64,
+    fallback_records: AtomicU64,
+    filtered_records: AtomicU64,
+}
+
+// A record that passed the filters, with its lines rendered and ready to be written
+struct PreparedMessage<'a> {
+    record: LogRecord<'a>,
+    file_line: String,
+    // Console stream and line when the level goes to the console; no line means the file line is used as is
+    console: Option<(ConsoleTarget, Option<String>)>,
+}
+
+// The open log file and the path it was opened from
+struct LogFile {
+    file: fs::File,
+    path: PathBuf,
+}
+
+// Serialises console output across all loggers, so lines on stdout and stderr never interleave
+static CONSOLE_LOCK: Mutex<()> = Mutex::new(());
+
+// Struct to represent the logger
+pub struct Logger {
+    file: Mutex<Option<LogFile>>,
+    color_enabled: bool,
+    default_file_path: Option<PathBuf>,
+    default_max_file_size: u64,
+    redacted_keys: Vec<Regex>,
+    redacted_values: Vec<(Regex, String)>,
+    max_message_len: Option<usize>,
+    context_providers: Vec<Box<dyn ContextProvider>>,
+    stats: Stats,
+    console_levels: HashMap<LogLevel, bool>,
+    storage_levels: HashMap<LogLevel, bool>,
+    compact: bool,
+    field_key_color: Option<LogColor>,
+    field_value_color: Option<LogColor>,
+    force_color: bool,
+    level_styles: HashMap<LogLevel, LevelStyle>,
+    console_enabled: bool,
+    file_enabled: bool,
+    file_error_action: FileErrorAction,
+    console_target: ConsoleTarget,
+    level_console_targets: HashMap<LogLevel, ConsoleTarget>,
+    multiline_policy: MultilinePolicy,
+    console_multiline_policy: MultilinePolicy,
+    custom_sinks: Vec<Box<dyn LogSink>>,
+    formatter: Option<Box<dyn RecordFormatter>>,
+    level_override: Option<LevelOverride>,
+    host: Option<String>,
+    include_pid: bool,
+    write_lock: Mutex<()>,
+    interpolate_message: bool,
+    pad_level: bool,
+    file_mode: Option<u32>,
+    line_ending: LineEnding,
+    console_line_ending: LineEnding,
+    field_filters: Vec<(String, String)>,
+    filter: Option<RecordFilter>,
+    muted_keys: Vec<String>,
+    filter_directives: FilterDirectives,
+    color_environment: ColorEnvironment,
+}
+
+// Width of the longest level name ("Critical")
+const LEVEL_NAME_WIDTH: usize = 8;
+
+// Times the panic hook tries the write lock, a millisecond apart, before writing to stderr instead
+const PANIC_HOOK_LOCK_ATTEMPTS: u32 = 100;
+
+impl Logger {
+    // Create a new Logger instance
+    pub fn new() -> Self {
+        Logger {
+            file: Mutex::new(None),
+            color_enabled: true,
+            default_file_path: None,
+            default_max_file_size: 100,
+            redacted_keys: Vec::new(),
+            redacted_values: Vec::new(),
+            max_message_len: None,
+            context_providers: Vec::new(),
+            stats: Stats::default(),
+            console_levels: HashMap::new(),
+            storage_levels: HashMap::new(),
+            compact: false,
+            field_key_color: None,
+            field_value_color: None,
+            force_color: false,
+            level_styles: HashMap::new(),
+            console_enabled: true,
+            file_enabled: true,
+            file_error_action: FileErrorAction::default(),
+            console_target: ConsoleTarget::default(),
+            level_console_targets: HashMap::new(),
+            multiline_policy: MultilinePolicy::Escape,
+            console_multiline_policy: MultilinePolicy::Raw,
+            custom_sinks: Vec::new(),
+            formatter: None,
+            level_override: None,
+            host: None,
+            include_pid: false,
+            write_lock: Mutex::new(()),
+            interpolate_message: false,
+            pad_level: false,
+            file_mode: None,
+            line_ending: LineEnding::Lf,
+            console_line_ending: LineEnding::Lf,
+            field_filters: Vec::new(),
+            filter: None,
+            muted_keys: Vec::new(),
+            filter_directives: FilterDirectives::default(),
+            color_environment: ColorEnvironment::detect(),
+        }
+    }
+
+    // Create a builder to configure a Logger in one expression
+    pub fn builder() -> LoggerBuilder {
+        LoggerBuilder::new()
+    }
+
+    // Start logging (open the log file)
+    pub fn start_logging(&self, file_path: &str) -> std::io::Result<()> {
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        if let Some(mode) = self.file_mode {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(mode);
+        }
+
+        let file = options.open(file_path).map_err(|err| log_file_error(file_path, err))?;
+        *self.log_file() = Some(LogFile { file, path: PathBuf::from(file_path) });
+        Ok(())
+    }
+
+    // Stop logging (close the log file)
+    pub fn stop_logging(&self) {
+        *self.log_file() = None;
+    }
+
+    // Lock the log file, also after a panic while it was held (a plain file handle has no state to corrupt),
+    // so flushing on drop during unwinding cannot panic again
+    fn log_file(&self) -> MutexGuard<'_, Option<LogFile>> {
+        self.file.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    // Set default file path and max file size
+    pub fn set_default_file_path(&mut self, path: &str) {
+        self.default_file_path = Some(PathBuf::from(path));
+    }
+
+    pub fn set_default_max_file_size(&mut self, max_size: u64) {
+        self.default_max_file_size = max_size;
+    }
+
+    // Permission bits (e.g. 0o640, still masked by the umask) for log files created by start_logging; ignored off Unix
+    pub fn set_file_mode(&mut self, mode: Option<u32>) {
+        self.file_mode = mode;
+    }
+
+    /// Format a message exactly as it would be printed and stored, without logging it.
+    ///
+    /// ```
+    /// use logly::logly::*;
+    ///
+    /// let mut logly = Logger::new();
+    /// logly.set_color_enabled(false);
+    ///
+    /// let line = logly.format_record(LogLevel::Warn, "Key1", "Value1", LogColor::Yellow);
+    /// assert_eq!(line, "[Warn]: Key1 - Value1\n");
+    /// ```
+    pub fn format_record(&self, level: LogLevel, key: &str, value: &str, color: LogColor) -> String {
+        let record = self.build_record(level, key, value, &[], self.context(), color);
+        self.render(&record, self.file_line_options())
+    }
+
+    // Format a message as it would be printed to the console (colored per console_colors_enabled)
+    pub fn format_console_record(&self, level: LogLevel, key: &str, value: &str, color: LogColor) -> String {
+        let record = self.build_record(level, key, value, &[], self.context(), color);
+        self.render(&record, self.console_line_options(self.console_target(record.level)))
+    }
+
+    // Context fields of a record logged now on this thread: host, pid, then the context providers' fields
+    fn context(&self) -> Vec<(String, String)> {
+        let mut context = Vec::new();
+        if let Some(host) = &self.host {
+            context.push(("host".to_string(), host.clone()));
+        }
+        if self.include_pid {
+            context.push(("pid".to_string(), std::process::id().to_string()));
+        }
+        for provider in &self.context_providers {
+            provider.enrich(&mut context);
+        }
+        context
+    }
+
+    // Build the record for a message: redacted and truncated, with the given context fields added
+    fn build_record<'a>(
+        &self,
+        level: LogLevel,
+        key: &'a str,
+        value: &'a str,
+        fields: &[(&'a str, &'a str)],
+        context: Vec<(String, String)>,
+        color: LogColor,
+    ) -> LogRecord<'a> {
+        let value = self.redact(key, value);
+
+        // One-shot fields then context fields, in order
+        let fields: Vec<(Cow<'a, str>, Cow<'a, str>)> = fields
+            .iter()
+            .map(|&(field_key, field_value)| (Cow::Borrowed(field_key), self.redact(field_key, field_value)))
+            .chain(context.into_iter().map(|(field_key, field_value)| {
+                let field_value = self.redact(&field_key, &field_value).into_owned();
+                (Cow::Owned(field_key), Cow::Owned(field_value))
+            }))
+            .collect();
+
+        let value = if self.interpolate_message {
+            interpolate(value, &fields)
+        } else {
+            value
+        };
+        let value = self.truncate(value);
+
+        let mut record = LogRecord {
+            level,
+            key,
+            value,
+            fields,
+            color,
+        };
+        if let Some(new_level) = self.level_override.as_ref().and_then(|level_override| level_override(&record)) {
+            record.level = new_level;
+        }
+        record
+    }
+
+    // Build the line for a record with the given rendering options
+    fn render(&self, record: &LogRecord, options: LineOptions) -> String {
+        match &self.formatter {
+            Some(formatter) => formatter.format(record) + options.line_ending.as_str(),
+            None => self.render_builtin(record, options),
+        }
+    }
+
+    // Build the line for a record with the built-in layout
+    fn render_builtin(&self, record: &LogRecord, options: LineOptions) -> String {
+        let colored = options.colored;
+        let color_code = match self.level_styles.get(&record.level) {
+            Some(style) if colored => style.ansi_code(record.color),
+            _ if colored => record.color.ansi_code(),
+            _ => String::new(),
+        };
+
+        let reset_color = if colored { "\x1b[0m" } else { "" };
+
+        let value = options.multiline.apply(Cow::Borrowed(&record.value));
+
+        // Field keys and values get their own color when set, then the line color resumes
+        let paint = |field_color: Option<LogColor>, text: &str| match field_color {
+            Some(field_color) if colored => {
+                format!("{}{}{}{}", field_color.ansi_code(), text, reset_color, color_code)
+            }
+            _ => text.to_string(),
+        };
+
+        // Fields are appended as key=value pairs in order
+        let fields: String = record
+            .fields
+            .iter()
+            .map(|(field_key, field_value)| {
+                format!(
+                    " {}={}",
+                    paint(self.field_key_color, field_key),
+                    paint(self.field_value_color, &options.multiline.apply(Cow::Borrowed(field_value)))
+                )
+            })
+            .collect();
+
+        if self.compact {
+            return format!(
+                "{}{} {}={}{}{}{}",
+                color_code,
+                record.level.short_name(),
+                record.key,
+                value,
+                fields,
+                reset_color,
+                options.line_ending.as_str()
+            );
+        }
+
+        // Padding is applied to the level name itself, so escape codes don't count towards the width
+        let level_width = if self.pad_level { LEVEL_NAME_WIDTH } else { 0 };
+        format!(
+            "{}[{:<level_width$}]: {} - {}{}{}{}{}",
+            color_code,
+            record.level,
+            record.key,
+            value,
+            fields,
+            reset_color,
+            reset_color,
+            options.line_ending.as_str()
+        )
+    }
+
+    // Mask a value whose key matches a redacted key, or the parts matching a redacted value pattern
+    fn redact<'a>(&self, key: &str, value: &'a str) -> Cow<'a, str> {
+        if self.redacted_keys.iter().any(|pattern| pattern.is_match(key)) {
+            return Cow::Borrowed("***");
+        }
+
+        let mut value = Cow::Borrowed(value);
+        for (pattern, replacement) in &self.redacted_values {
+            if pattern.is_match(&value) {
+                value = Cow::Owned(pattern.replace_all(&value, replacement.as_str()).into_owned());
+            }
+        }
+        value
+    }
+
+    // Cut the value to max_message_len bytes on a char boundary, noting how much was dropped
+    fn truncate<'a>(&self, value: Cow<'a, str>) -> Cow<'a, str> {
+        let max_len = match self.max_message_len {
+            Some(max_len) if value.len() > max_len => max_len,
+            _ => return value,
+        };
+
+        let mut end = max_len;
+        while !value.is_char_boundary(end) {
+            end -= 1;
+        }
+
+        Cow::Owned(format!("{}… (truncated {} bytes)", &value[..end], value.len() - end))
+    }
+
+    // Log a message with a specified level and color
+    fn log_message(&self, level: LogLevel, key: &str, value: &str, fields: &[(&str, &str)], color: LogColor) {
+        self.log_in_context(level, key, value, fields, None, color);
+    }
+
+    // Log a message with the given context fields, or the current ones when None
+    fn log_in_context(
+        &self,
+        level: LogLevel,
+        key: &str,
+        value: &str,
+        fields: &[(&str, &str)],
+        context: Option<&[(String, String)]>,
+        color: LogColor,
+    ) {
+        let _logging = LoggingGuard::enter();
+        // Records are built, filtered and rendered before taking the write lock, so callbacks may log themselves
+        let Some(message) = self.prepare(level, key, value, fields, context, color) else {
+            return;
+        };
+        if EmitGuard::is_emitting(self) {
+            // Logged from a custom sink of this Logger: this thread already holds the write lock
+            let _ = io::stderr().write_all(message.file_line.as_bytes());
+            return;
+        }
+
+        let _write = self.write_lock.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let _emitting = EmitGuard::enter(self);
+        self.emit(&message);
+    }
+
+    // Build, filter and render one message; None when the filters drop it
+    fn prepare<'a>(
+        &self,
+        level: LogLevel,
+        key: &'a str,
+        value: &'a str,
+        fields: &[(&'a str, &'a str)],
+        context: Option<&[(String, String)]>,
+        color: LogColor,
+    ) -> Option<PreparedMessage<'a>> {
+        if !self.enabled(key, level) {
+            self.stats.filtered_records.fetch_add(1, Ordering::Relaxed);
+            return None;
+        }
+        let context = context.map_or_else(|| self.context(), <[_]>::to_vec);
+        let record = self.build_record(level, key, value, fields, context, color);
+        if !self.accepts(&record) {
+            self.stats.filtered_records.fetch_add(1, Ordering::Relaxed);
+            return None;
+        }
+
+        // The level override may have changed the level used for routing and display
+        let level = record.level;
+        self.stats.records[level as usize].fetch_add(1, Ordering::Relaxed);
+
+        let file_options = self.file_line_options();
+        let console_target = (self.console_enabled && self.console_levels.get(&level).copied().unwrap_or(true))
+            .then(|| self.console_target(level));
+        let console_options = console_target.map(|target| self.console_line_options(target));
+
+        let (file_line, console_line) = match &self.formatter {
+            // The formatter runs once per record; each destination only adds its own line ending
+            Some(formatter) => {
+                let line = formatter.format(&record);
+                let console_line = console_options
+                    .filter(|options| options.line_ending != file_options.line_ending)
+                    .map(|options| line.clone() + options.line_ending.as_str());
+                (line + file_options.line_ending.as_str(), console_line)
+            }
+            None => {
+                let console_line = console_options
+                    .filter(|options| !options.renders_like(&file_options, &record))
+                    .map(|options| self.render_builtin(&record, options));
+                (self.render_builtin(&record, file_options), console_line)
+            }
+        };
+
+        Some(PreparedMessage {
+            record,
+            file_line,
+            console: console_target.map(|target| (target, console_line)),
+        })
+    }
+
+    // Write one prepared message to every output; the caller holds the write lock
+    fn emit(&self, message: &PreparedMessage) {
+        let level = message.record.level;
+        for sink in &self.custom_sinks {
+            if let Err(err) = sink.write(&message.record) {
+                self.stats.write_errors.fetch_add(1, Ordering::Relaxed);
+                eprintln!("Error writing to custom sink: {}", err);
+            }
+        }
+
+        if let Some((target, console_line)) = &message.console {
+            let console_line = console_line.as_ref().unwrap_or(&message.file_line);
+
+            // Each line is a single pre-formatted write, done while holding the console lock
+            let _console = CONSOLE_LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            match target {
+                ConsoleTarget::Stdout => print!("{}", console_line),
+                ConsoleTarget::Stderr => eprint!("{}", console_line),
+            }
+        }
+
+        if !self.file_enabled || !self.storage_levels.get(&level).copied().unwrap_or(true) {
+            return;
+        }
+
+        // Write to the log file if it's open
+        if let Some(ref mut log_file) = *self.log_file() {
+            if let Err(err) = log_file.file.write_all(message.file_line.as_bytes()) {
+                self.stats.write_errors.fetch_add(1, Ordering::Relaxed);
+                eprintln!("Error writing to log file: {}", err);
+
+                if self.file_error_action == FileErrorAction::Fallback {
+                    self.stats.fallback_records.fetch_add(1, Ordering::Relaxed);
+                    eprint!("{}", message.file_line);
+                }
+            }
+        }
+    }
+
+    // Log from the panic hook without blocking on a lock this thread may hold: a panic inside a logging call
+    // is written straight to stderr, and the write lock is waited for briefly before falling back to stderr too
+    fn log_from_panic_hook(&self, value: &str, fields: &[(&str, &str)]) {
+        if LoggingGuard::is_logging() {
+            let fields: String = fields
+                .iter()
+                .map(|(field_key, field_value)| format!(" {}={}", field_key, field_value))
+                .collect();
+            let _ = writeln!(io::stderr(), "[{}]: panic - {}{}", LogLevel::Critical, value, fields);
+            return;
+        }
+
+        let _logging = LoggingGuard::enter();
+        let color = LogLevel::Critical.default_color();
+        let Some(message) = self.prepare(LogLevel::Critical, "panic", value, fields, None, color) else {
+            return;
+        };
+        for _ in 0..PANIC_HOOK_LOCK_ATTEMPTS {
+            let _write = match self.write_lock.try_lock() {
+                Ok(write) => write,
+                Err(TryLockError::Poisoned(poisoned)) => poisoned.into_inner(),
+                Err(TryLockError::WouldBlock) => {
+                    std::thread::sleep(Duration::from_millis(1));
+                    continue;
+                }
+            };
+            let _emitting = EmitGuard::enter(self);
+            self.emit(&message);
+            return;
+        }
+        let _ = io::stderr().write_all(message.file_line.as_bytes());
+    }
+
+    // Whether a message with this key and level gets past the key mutes and filter directives, checked before
+    // the record is built, so callers can skip building expensive messages. With a level override set the
+    // directives are only known to apply to the effective level, so just the key mutes are checked here
+    pub fn enabled(&self, key: &str, level: LogLevel) -> bool {
+        let muted = self.muted_keys.iter().any(|muted_key| key_in_module(key, muted_key));
+        !muted && (self.level_override.is_some() || self.filter_directives.allows(key, level))
+    }
+
+    // Most verbose level the filter directives let through for some key, or None when they turn everything off
+    pub(crate) fn max_enabled_level(&self) -> Option<LogLevel> {
+        if self.level_override.is_some() {
+            return Some(LogLevel::Trace);
+        }
+        self.filter_directives.most_verbose()
+    }
+
+    // Whether a built record passes the global filter and field filters, and the filter directives on its
+    // effective level when a level override may have changed it; dropped records reach no output
+    fn accepts(&self, record: &LogRecord) -> bool {
+        if self.filter.as_ref().is_some_and(|filter| !filter(record)) {
+            return false;
+        }
+        if self.level_override.is_some() && !self.filter_directives.allows(record.key, record.level) {
+            return false;
+        }
+
+        self.field_filters.iter().all(|(filter_key, filter_value)| {
+            record
+                .fields
+                .iter()
+                .any(|(field_key, field_value)| field_key == filter_key && field_value == filter_value)
+        })
+    }
+
+    // Whether console output is colored: force_color wins, then NO_COLOR, CLICOLOR_FORCE and finally whether the console
+    // is a terminal. color_enabled and force_color are read on every line; the environment as detected when the
+    // Logger was created (see set_color_environment)
+    pub fn console_colors_enabled(&self) -> bool {
+        self.console_colors_for(self.console_target)
+    }
+
+    fn console_colors_for(&self, target: ConsoleTarget) -> bool {
+        if !self.color_enabled {
+            return false;
+        }
+        if self.force_color {
+            return true;
+        }
+        if self.color_environment.no_color {
+            return false;
+        }
+        if self.color_environment.clicolor_force {
+            return true;
+        }
+        self.color_environment.is_terminal(target)
+    }
+
+    // Replace the detected color environment, e.g. ColorEnvironment::detect() again after changing NO_COLOR
+    pub fn set_color_environment(&mut self, environment: ColorEnvironment) {
+        self.color_environment = environment;
+    }
+
+    // Stream the console output of a level goes to
+    pub fn console_target(&self, level: LogLevel) -> ConsoleTarget {
+        self.level_console_targets.get(&level).copied().unwrap_or(self.console_target)
+    }
+
+    // Rendering options of console lines written to the given stream (and format_console_record)
+    fn console_line_options(&self, target: ConsoleTarget) -> LineOptions {
+        LineOptions {
+            colored: self.console_colors_for(target),
+            multiline: self.console_multiline_policy,
+            line_ending: self.console_line_ending,
+        }
+    }
+
+    // Rendering options of the log file (and format_record)
+    fn file_line_options(&self) -> LineOptions {
+        LineOptions {
+            colored: self.color_enabled,
+            multiline: self.multiline_policy,
+            line_ending: self.line_ending,
+        }
+    }
+
+    // Describe the effective configuration, e.g. to print at startup (redaction patterns are not shown)
+    pub fn diagnostics(&self) -> String {
+        let on_off = |enabled: bool| if enabled { "enabled" } else { "muted" };
+        let levels_off = |levels: &HashMap<LogLevel, bool>| {
+            let off: Vec<String> = LogLevel::ALL
+                .iter()
+                .filter(|level| levels.get(level) == Some(&false))
+                .map(|level| level.to_string())
+                .collect();
+            if off.is_empty() { "none".to_string() } else { off.join(", ") }
+        };
+
+        let log_file = match &*self.log_file() {
+            Some(log_file) => format!("{} ({})", log_file.path.display(), on_off(self.file_enabled)),
+            None => "none".to_string(),
+        };
+        let max_message_len = self
+            .max_message_len
+            .map_or("none".to_string(), |max_len| format!("{} bytes", max_len));
+        let default_file_path = self
+            .default_file_path
+            .as_ref()
+            .map_or("none".to_string(), |path| path.display().to_string());
+
+        let report = [
+            format!("logly {}", env!("CARGO_PKG_VERSION")),
+            format!("log file: {}", log_file),
+            format!("console: {:?} ({})", self.console_target, on_off(self.console_enabled)),
+            format!(
+                "colors: {} (console: {}, forced: {})",
+                self.color_enabled,
+                self.console_colors_enabled(),
+                self.force_color
+            ),
+            format!("levels off on console: {}", levels_off(&self.console_levels)),
+            format!("levels off in file: {}", levels_off(&self.storage_levels)),
+            format!("compact: {}, padded levels: {}", self.compact, self.pad_level),
+            format!("message interpolation: {}", self.interpolate_message),
+            format!("multiline: file {:?}, console {:?}", self.multiline_policy, self.console_multiline_policy),
+            format!("line ending: file {:?}, console {:?}", self.line_ending, self.console_line_ending),
+            format!("max message length: {}", max_message_len),
+            format!("redaction rules: {} key, {} value", self.redacted_keys.len(), self.redacted_values.len()),
+            format!("host field: {}, pid field: {}", self.host.is_some(), self.include_pid),
+            format!("context providers: {}", self.context_providers.len()),
+            format!("custom sinks: {}", self.custom_sinks.len()),
+            format!("custom formatter: {}", self.formatter.is_some()),
+            format!("level override: {}", self.level_override.is_some()),
+            format!(
+                "filters: {} field, {} muted keys, {} directives, global filter: {}",
+                self.field_filters.len(),
+                self.muted_keys.len(),
+                self.filter_directives.modules.len() + usize::from(self.filter_directives.default.is_some()),
+                self.filter.is_some()
+            ),
+            format!("on file error: {:?}", self.file_error_action),
+            format!("default file path: {}", default_file_path),
+            format!("default max file size: {}", self.default_max_file_size),
+            format!("file mode: {}", self.file_mode.map_or("default".to_string(), |mode| format!("{:o}", mode))),
+        ];
+        report.join("\n") + "\n"
+    }
+
+    // Start a group of messages written together, without other messages in between, on commit
+    pub fn transaction(&self) -> LogTransaction<'_> {
+        LogTransaction {
+            logger: self,
+            messages: Vec::new(),
+        }
+    }
+
+    // Time a block of work: the span logs an Info "completed" message with a duration_ms field when dropped.
+    // Its messages carry the context fields of when it was created, even if dropped on another thread
+    pub fn span(&self, key: &str) -> LogSpan<'_> {
+        LogSpan::new(self, key, Vec::new())
+    }
+
+    // Get a handle logging through this Logger with its own bound fields
+    pub fn child(&self) -> ChildLogger<'_> {
+        ChildLogger {
+            logger: self,
+            fields: Vec::new(),
+        }
+    }
+
+    // Get a writer turning each line written to it into a message at the given level
+    pub fn writer<'a>(&'a self, level: LogLevel, key: &str, color: LogColor) -> LoglyWriter<'a> {
+        LoglyWriter {
+            logger: self,
+            level,
+            key: key.to_string(),
+            color,
+            buffer: Vec::new(),
+        }
+    }
+
+    // Get a copy of the settings currently in effect
+    pub fn config_snapshot(&self) -> LoggerConfig {
+        LoggerConfig {
+            log_file: self.log_file().as_ref().map(|log_file| log_file.path.clone()),
+            color_enabled: self.color_enabled,
+            force_color: self.force_color,
+            level_styles: self.level_styles.clone(),
+            field_key_color: self.field_key_color,
+            field_value_color: self.field_value_color,
+            compact: self.compact,
+            pad_level: self.pad_level,
+            console_enabled: self.console_enabled,
+            file_enabled: self.file_enabled,
+            console_target: self.console_target,
+            level_console_targets: self.level_console_targets.clone(),
+            console_levels: self.console_levels.clone(),
+            storage_levels: self.storage_levels.clone(),
+            multiline_policy: self.multiline_policy,
+            console_multiline_policy: self.console_multiline_policy,
+            line_ending: self.line_ending,
+            console_line_ending: self.console_line_ending,
+            max_message_len: self.max_message_len,
+            interpolate_message: self.interpolate_message,
+            field_filters: self.field_filters.clone(),
+            muted_keys: self.muted_keys.clone(),
+            include_host: self.host.is_some(),
+            include_pid: self.include_pid,
+            file_error_action: self.file_error_action,
+            file_mode: self.file_mode,
+            default_file_path: self.default_file_path.clone(),
+            default_max_file_size: self.default_max_file_size,
+        }
+    }
+
+    // Get a snapshot of the records logged per level and the log file write errors
+    pub fn stats(&self) -> LoggerStats {
+        let records_by_level: HashMap<LogLevel, u64> = LogLevel::ALL
+            .iter()
+            .map(|&level| (level, self.stats.records[level as usize].load(Ordering::Relaxed)))
+            .collect();
+
+        LoggerStats {
+            total_records: records_by_level.values().sum(),
+            records_by_level,
+            write_errors: self.stats.write_errors.load(Ordering::Relaxed),
+            fallback_records: self.stats.fallback_records.load(Ordering::Relaxed),
+            filtered_records: self.stats.filtered_records.load(Ordering::Relaxed),
+        }
+    }
+
+    // Reset all counters to zero
+    pub fn reset_stats(&self) {
+        for counter in &self.stats.records {
+            counter.store(0, Ordering::Relaxed);
+        }
+        self.stats.write_errors.store(0, Ordering::Relaxed);
+        self.stats.fallback_records.store(0, Ordering::Relaxed);
+        self.stats.filtered_records.store(0, Ordering::Relaxed);
+    }
+
+    // Log methods for various levels and colors
+    pub fn info(&self, key: &str, value: &str, color: LogColor) {
+        self.log_message(LogLevel::Info, key, value, &[], color);
+    }
+
+    pub fn warn(&self, key: &str, value: &str, color: LogColor) {
+        self.log_message(LogLevel::Warn, key, value, &[], color);
+    }
+
+    pub fn error(&self, key: &str, value: &str, color: LogColor) {
+        self.log_message(LogLevel::Error, key, value, &[], color);
+    }
+
+    pub fn debug(&self, key: &str, value: &str, color: LogColor) {
+        self.log_message(LogLevel::Debug, key, value, &[], color);
+    }
+
+    pub fn critical(&self, key: &str, value: &str, color: LogColor) {
+        self.log_message(LogLevel::Critical, key, value, &[], color);
+    }
+
+    pub fn fatal(&self, key: &str, value: &str, color: LogColor) {
+        self.log_message(LogLevel::Fatal, key, value, &[], color);
+    }
+
+    pub fn trace(&self, key: &str, value: &str, color: LogColor) {
+        self.log_message(LogLevel::Trace, key, value, &[], color);
+    }
+
+    pub fn log(&self, key: &str, value: &str, color: LogColor) {
+        self.log_message(LogLevel::Info, key, value, &[], color);
+    }
+
+    // Log panics as Critical messages (key "panic") with their location, optionally still running the previous hook.
+    // The hook keeps the Logger for the rest of the program, e.g. the global one: logly::global().unwrap()
+    pub fn install_panic_hook(&'static self, chain_previous: bool) {
+        let logger = self;
+        let previous = std::panic::take_hook();
+
+        std::panic::set_hook(Box::new(move |info| {
+            let payload = info.payload();
+            let message = payload
+                .downcast_ref::<&str>()
+                .copied()
+                .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+                .unwrap_or("Box<dyn Any>");
+            let location = info
+                .location()
+                .map(|location| format!("{}:{}:{}", location.file(), location.line(), location.column()))
+                .unwrap_or_else(|| "unknown".to_string());
+            let thread = std::thread::current();
+            let thread = thread.name().unwrap_or("<unnamed>");
+
+            logger.log_from_panic_hook(message, &[("location", &location), ("thread", thread)]);
+
+            if chain_previous {
+                previous(info);
+            }
+        }));
+    }
+
+    // Log an error with its source chain as fields: error, error.source.0, error.source.1, ...
+    pub fn error_err(&self, key: &str, err: &dyn std::error::Error) {
+        let message = err.to_string();
+        let mut chain = vec![("error".to_string(), message.clone())];
+        let mut source = err.source();
+        while let Some(cause) = source {
+            chain.push((format!("error.source.{}", chain.len() - 1), cause.to_string()));
+            source = cause.source();
+        }
+
+        let fields: Vec<(&str, &str)> = chain.iter().map(|(name, text)| (name.as_str(), text.as_str())).collect();
+        self.log_message(LogLevel::Error, key, &message, &fields, LogLevel::Error.default_color());
+    }
+
+    // Log a message with extra fields attached to this record only
+    pub fn log_with(&self, level: LogLevel, key: &str, value: &str, fields: &[(&str, &str)], color: LogColor) {
+        self.log_message(level, key, value, fields, color);
+    }
+
+    pub fn info_with(&self, key: &str, value: &str, fields: &[(&str, &str)], color: LogColor) {
+        self.log_message(LogLevel::Info, key, value, fields, color);
+    }
+
+    pub fn warn_with(&self, key: &str, value: &str, fields: &[(&str, &str)], color: LogColor) {
+        self.log_message(LogLevel::Warn, key, value, fields, color);
+    }
+
+    pub fn error_with(&self, key: &str, value: &str, fields: &[(&str, &str)], color: LogColor) {
+        self.log_message(LogLevel::Error, key, value, fields, color);
+    }
+
+    pub fn debug_with(&self, key: &str, value: &str, fields: &[(&str, &str)], color: LogColor) {
+        self.log_message(LogLevel::Debug, key, value, fields, color);
+    }
+
+    pub fn critical_with(&self, key: &str, value: &str, fields: &[(&str, &str)], color: LogColor) {
+        self.log_message(LogLevel::Critical, key, value, fields, color);
+    }
+
+    pub fn fatal_with(&self, key: &str, value: &str, fields: &[(&str, &str)], color: LogColor) {
+        self.log_message(LogLevel::Fatal, key, value, fields, color);
+    }
+
+    pub fn trace_with(&self, key: &str, value: &str, fields: &[(&str, &str)], color: LogColor) {
+        self.log_message(LogLevel::Trace, key, value, fields, color);
+    }
+
+    // Set color enabled or disabled
+    pub fn set_color_enabled(&mut self, color_enabled: bool) {
+        self.color_enabled = color_enabled;
+    }
+
+    // Color console output even when stdout is not a terminal or NO_COLOR is set
+    pub fn set_force_color(&mut self, force_color: bool) {
+        self.force_color = force_color;
+    }
+
+    // Set the style (bold, italic, underline, colors) used for every message of a level
+    pub fn set_level_style(&mut self, level: LogLevel, style: LevelStyle) {
+        self.level_styles.insert(level, style);
+    }
+
+    // Set the colors of field keys and values (None keeps the line color)
+    pub fn set_field_colors(&mut self, key_color: Option<LogColor>, value_color: Option<LogColor>) {
+        self.field_key_color = key_color;
+        self.field_value_color = value_color;
+    }
+
+    // Set compact output (single-letter level, key=value) for dense consoles
+    pub fn set_compact(&mut self, compact: bool) {
+        self.compact = compact;
+    }
+
+    // Pad the level name to the width of the longest one, so the columns after it line up
+    pub fn set_pad_level(&mut self, pad_level: bool) {
+        self.pad_level = pad_level;
+    }
+
+    // Replace {field} placeholders in the message with the record's (redacted) field values
+    pub fn set_interpolate_message(&mut self, interpolate_message: bool) {
+        self.interpolate_message = interpolate_message;
+    }
+
+    // Mute or unmute the console output
+    pub fn set_console_enabled(&mut self, enabled: bool) {
+        self.console_enabled = enabled;
+    }
+
+    pub fn is_console_enabled(&self) -> bool {
+        self.console_enabled
+    }
+
+    // Mute or unmute the log file output, keeping the file open
+    pub fn set_file_enabled(&mut self, enabled: bool) {
+        self.file_enabled = enabled;
+    }
+
+    pub fn is_file_enabled(&self) -> bool {
+        self.file_enabled
+    }
+
+    // Set how line breaks in values are written to the log file (escaped by default)
+    pub fn set_multiline_policy(&mut self, policy: MultilinePolicy) {
+        self.multiline_policy = policy;
+    }
+
+    // Set how line breaks in values are printed to the console (unchanged by default)
+    pub fn set_console_multiline_policy(&mut self, policy: MultilinePolicy) {
+        self.console_multiline_policy = policy;
+    }
+
+    // Set the terminator of each line in the log file (\n by default)
+    pub fn set_line_ending(&mut self, line_ending: LineEnding) {
+        self.line_ending = line_ending;
+    }
+
+    // Set the terminator of each line printed to the console (\n by default)
+    pub fn set_console_line_ending(&mut self, line_ending: LineEnding) {
+        self.console_line_ending = line_ending;
+    }
+
+    // Choose what happens to a record when writing it to the log file fails
+    pub fn set_file_error_action(&mut self, action: FileErrorAction) {
+        self.file_error_action = action;
+    }
+
+    // Write the console output to stdout (the default) or stderr
+    pub fn set_console_target(&mut self, target: ConsoleTarget) {
+        self.console_target = target;
+    }
+
+    // Write the console output of one level to stdout or stderr, e.g. errors to stderr
+    pub fn set_level_console_target(&mut self, level: LogLevel, target: ConsoleTarget) {
+        self.level_console_targets.insert(level, target);
+    }
+
+    // Choose whether messages of a level are printed to the console (all levels are by default)
+    pub fn set_console_level(&mut self, level: LogLevel, enabled: bool) {
+        self.console_levels.insert(level, enabled);
+    }
+
+    // Choose whether messages of a level are stored in the log file (all levels are by default)
+    pub fn set_storage_level(&mut self, level: LogLevel, enabled: bool) {
+        self.storage_levels.insert(level, enabled);
+    }
+
+    // Limit the message value to max_len bytes (None for no limit)
+    pub fn set_max_message_len(&mut self, max_len: Option<usize>) {
+        self.max_message_len = max_len;
+    }
+
+    // Render console and file lines with a custom formatter instead of the built-in layout (None restores it)
+    pub fn set_formatter(&mut self, formatter: Option<Box<dyn RecordFormatter>>) {
+        self.formatter = formatter;
+    }
+
+    // Only log records with a field key equal to value (after context fields and redaction); records without the field are dropped
+    pub fn add_field_filter(&mut self, key: &str, value: &str) {
+        self.field_filters.push((key.to_string(), value.to_string()));
+    }
+
+    // Drop every record the filter returns false for, on all outputs
+    pub fn set_global_filter(&mut self, filter: Option<RecordFilter>) {
+        self.filter = filter;
+    }
+
+    // Drop records whose key is the given one or a module path below it ("hyper" mutes "hyper::client" too)
+    pub fn add_key_mute(&mut self, key: &str) {
+        self.muted_keys.push(key.to_string());
+    }
+
+    // Filter by key like RUST_LOG, e.g. "warn,myapp=info,myapp::db=debug": the most specific module
+    // (the key or a parent path of it) sets the least severe level logged, a bare level applies to other
+    // keys, and keys matching nothing pass unless a bare level is given. An empty string removes the directives.
+    pub fn set_filter_directives(&mut self, directives: &str) -> Result<(), ParseLevelError> {
+        self.filter_directives = FilterDirectives::parse(directives)?;
+        Ok(())
+    }
+
+    // Apply the directives in LOGLY_FILTER, or else RUST_LOG; returns whether one of them was set
+    pub fn set_filter_directives_from_env(&mut self) -> Result<bool, ParseLevelError> {
+        match std::env::var("LOGLY_FILTER").or_else(|_| std::env::var("RUST_LOG")) {
+            Ok(directives) => self.set_filter_directives(&directives).map(|_| true),
+            Err(_) => Ok(false),
+        }
+    }
+
+    // Re-level matching records (e.g. anything mentioning "panic" becomes Critical) before they are routed and displayed
+    pub fn set_level_override(&mut self, level_override: Option<LevelOverride>) {
+        self.level_override = level_override;
+    }
+
+    // Register a sink that receives every record alongside the console and the log file
+    pub fn add_custom_sink(&mut self, sink: Box<dyn LogSink>) {
+        self.custom_sinks.push(sink);
+    }
+
+    // Flush the log file and all custom sinks
+    pub fn flush(&self) -> io::Result<()> {
+        if let Some(ref mut log_file) = *self.log_file() {
+            log_file.file.flush()?;
+        }
+        for sink in &self.custom_sinks {
+            sink.flush()?;
+        }
+        Ok(())
+    }
+
+    // Register a provider whose fields are added to every record
+    pub fn add_context_provider(&mut self, provider: Box<dyn ContextProvider>) {
+        self.context_providers.push(provider);
+    }
+
+    // Add the hostname (looked up once, here) as a "host" field to every record
+    pub fn set_include_host(&mut self, include_host: bool) {
+        self.host = if include_host { Some(hostname()) } else { None };
+    }
+
+    // Add the process id as a "pid" field to every record
+    pub fn set_include_pid(&mut self, include_pid: bool) {
+        self.include_pid = include_pid;
+    }
+
+    // Set the trace context reported by TraceContext for records logged on this thread
+    pub fn set_trace_context(trace_id: &str, span_id: &str) {
+        TRACE_CONTEXT.with(|context| *context.borrow_mut() = Some((trace_id.to_string(), span_id.to_string())));
+    }
+
+    // Clear the trace context of this thread
+    pub fn clear_trace_context() {
+        TRACE_CONTEXT.with(|context| *context.borrow_mut() = None);
+    }
+
+    // Render the value of any key (or field key) matching the pattern as ***
+    pub fn add_redaction(&mut self, key_pattern: &str) -> Result<(), regex::Error> {
+        self.redacted_keys.push(Regex::new(key_pattern)?);
+        Ok(())
+    }
+
+    // Replace the parts of any value (or field value) matching the pattern
+    pub fn add_redaction_regex(&mut self, value_pattern: &str, replacement: &str) -> Result<(), regex::Error> {
+        self.redacted_values.push((Regex::new(value_pattern)?, replacement.to_string()));
+        Ok(())
+    }
+}
+
+// Name of this machine, from the environment or the kernel, falling back to "localhost"
+pub(crate) fn hostname() -> String {
+    ["HOSTNAME", "COMPUTERNAME"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .chain(
+            ["/proc/sys/kernel/hostname", "/etc/hostname"]
+                .iter()
+                .filter_map(|path| fs::read_to_string(path).ok()),
+        )
+        .map(|name| name.trim().to_string())
+        .find(|name| !name.is_empty())
+        .unwrap_or_else(|| "localhost".to_string())
+}
+
+// Whether the key is the module or a path below it ("hyper::client" is in "hyper", "hyperlocal" is not)
+fn key_in_module(key: &str, module: &str) -> bool {
+    key.strip_prefix(module)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+}
+
+// Substitute {name} with the value of the first field named name; unknown placeholders are kept as written
+fn interpolate<'a>(value: Cow<'a, str>, fields: &[(Cow<str>, Cow<str>)]) -> Cow<'a, str> {
+    static PLACEHOLDER: OnceLock<Regex> = OnceLock::new();
+    let placeholder = PLACEHOLDER.get_or_init(|| Regex::new(r"\{([^{}\s]+)\}").unwrap());
+    if !placeholder.is_match(&value) {
+        return value;
+    }
+
+    let interpolated = placeholder.replace_all(&value, |captures: &regex::Captures| {
+        let name = &captures[1];
+        fields
+            .iter()
+            .find(|(field_key, _)| field_key == name)
+            .map_or_else(|| captures[0].to_string(), |(_, field_value)| field_value.to_string())
+    });
+    Cow::Owned(interpolated.into_owned())
+}
+
+// Explain why a log file could not be opened, keeping the original error kind
+fn log_file_error(file_path: &str, err: io::Error) -> io::Error {
+    let reason = match err.kind() {
+        io::ErrorKind::IsADirectory => "the path is a directory, pass a file path instead".to_string(),
+        io::ErrorKind::PermissionDenied => "permission denied, check write access to the file and its directory".to_string(),
+        io::ErrorKind::NotFound => "the parent directory does not exist".to_string(),
+        io::ErrorKind::ReadOnlyFilesystem => "the file system is mounted read-only".to_string(),
+        io::ErrorKind::StorageFull => "no space left on the device".to_string(),
+        _ => err.to_string(),
+    };
+
+    io::Error::new(err.kind(), format!("cannot open log file '{}': {}", file_path, reason))
+}
+
+impl Default for Logger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Flush the log file and custom sinks when the Logger goes away
+impl Drop for Logger {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+
+// io::Write adapter logging one message per line; a trailing partial line is logged on flush or drop
+pub struct LoglyWriter<'a> {
+    logger: &'a Logger,
+    level: LogLevel,
+    key: String,
+    color: LogColor,
+    buffer: Vec<u8>,
+}
+
+impl LoglyWriter<'_> {
+    fn log_line(&self, line: &[u8]) {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        self.logger
+            .log_message(self.level, &self.key, &String::from_utf8_lossy(line), &[], self.color);
+    }
+}
+
+impl Write for LoglyWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+
+        while let Some(newline) = self.buffer.iter().position(|&byte| byte == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=newline).collect();
+            self.log_line(&line[..newline]);
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.buffer.is_empty() {
+            let line = std::mem::take(&mut self.buffer);
+            self.log_line(&line);
+        }
+        Ok(())
+    }
+}
+
+impl Drop for LoglyWriter<'_> {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+// Timer logging the time since it was created when dropped
+pub struct LogSpan<'a> {
+    logger: &'a Logger,
+    key: String,
+    start: Instant,
+    // Bound fields of the ChildLogger it came from, and the context fields taken at creation
+    fields: Vec<(String, String)>,
+    context: Vec<(String, String)>,
+}
+
+impl<'a> LogSpan<'a> {
+    fn new(logger: &'a Logger, key: &str, fields: Vec<(String, String)>) -> Self {
+        LogSpan {
+            logger,
+            key: key.to_string(),
+            start: Instant::now(),
+            fields,
+            context: logger.context(),
+        }
+    }
+
+    // Log with the bound fields followed by the given ones, in the context of the span's creation
+    fn log(&self, level: LogLevel, value: &str, fields: &[(&str, &str)]) {
+        let fields: Vec<(&str, &str)> = self
+            .fields
+            .iter()
+            .map(|(field_key, field_value)| (field_key.as_str(), field_value.as_str()))
+            .chain(fields.iter().copied())
+            .collect();
+        self.logger
+            .log_in_context(level, &self.key, value, &fields, Some(&self.context), level.default_color());
+    }
+
+    // Also log a Debug "started" message now
+    pub fn log_start(self) -> Self {
+        self.log(LogLevel::Debug, "started", &[]);
+        self
+    }
+
+    // Time since the span was created
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+impl Drop for LogSpan<'_> {
+    fn drop(&mut self) {
+        let duration_ms = format!("{:.3}", self.elapsed().as_secs_f64() * 1000.0);
+        self.log(LogLevel::Info, "completed", &[("duration_ms", &duration_ms)]);
+    }
+}
+
+// A message held by a LogTransaction until commit
+struct PendingMessage {
+    level: LogLevel,
+    key: String,
+    value: String,
+    fields: Vec<(String, String)>,
+    // Context fields taken when the message was logged, not at commit
+    context: Vec<(String, String)>,
+    color: LogColor,
+}
+
+// Messages collected to be written in order as one block; dropping it without commit discards them
+pub struct LogTransaction<'a> {
+    logger: &'a Logger,
+    messages: Vec<PendingMessage>,
+}
+
+impl LogTransaction<'_> {
+    pub fn log_with(&mut self, level: LogLevel, key: &str, value: &str, fields: &[(&str, &str)], color: LogColor) {
+        self.messages.push(PendingMessage {
+            level,
+            key: key.to_string(),
+            value: value.to_string(),
+            fields: fields
+                .iter()
+                .map(|&(field_key, field_value)| (field_key.to_string(), field_value.to_string()))
+                .collect(),
+            context: self.logger.context(),
+            color,
+        });
+    }
+
+    pub fn info(&mut self, key: &str, value: &str, color: LogColor) {
+        self.log_with(LogLevel::Info, key, value, &[], color);
+    }
+
+    pub fn warn(&mut self, key: &str, value: &str, color: LogColor) {
+        self.log_with(LogLevel::Warn, key, value, &[], color);
+    }
+
+    pub fn error(&mut self, key: &str, value: &str, color: LogColor) {
+        self.log_with(LogLevel::Error, key, value, &[], color);
+    }
+
+    pub fn debug(&mut self, key: &str, value: &str, color: LogColor) {
+        self.log_with(LogLevel::Debug, key, value, &[], color);
+    }
+
+    pub fn critical(&mut self, key: &str, value: &str, color: LogColor) {
+        self.log_with(LogLevel::Critical, key, value, &[], color);
+    }
+
+    pub fn fatal(&mut self, key: &str, value: &str, color: LogColor) {
+        self.log_with(LogLevel::Fatal, key, value, &[], color);
+    }
+
+    pub fn trace(&mut self, key: &str, value: &str, color: LogColor) {
+        self.log_with(LogLevel::Trace, key, value, &[], color);
+    }
+
+    // Number of messages waiting for commit
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    // Write all messages in order while holding the Logger's write lock (they are built and rendered before it is
+    // taken, each with the context fields of when it was logged)
+    pub fn commit(self) {
+        let _logging = LoggingGuard::enter();
+        let prepared: Vec<PreparedMessage> = self
+            .messages
+            .iter()
+            .filter_map(|message| {
+                let fields: Vec<(&str, &str)> = message
+                    .fields
+                    .iter()
+                    .map(|(field_key, field_value)| (field_key.as_str(), field_value.as_str()))
+                    .collect();
+                let context = Some(message.context.as_slice());
+                self.logger
+                    .prepare(message.level, &message.key, &message.value, &fields, context, message.color)
+            })
+            .collect();
+        if EmitGuard::is_emitting(self.logger) {
+            for message in &prepared {
+                let _ = io::stderr().write_all(message.file_line.as_bytes());
+            }
+            return;
+        }
+
+        let _write = self.logger.write_lock.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let _emitting = EmitGuard::enter(self.logger);
+        for message in &prepared {
+            self.logger.emit(message);
+        }
+    }
+}
+
+// Handle adding its bound fields to every message it logs; the parent Logger is not affected
+pub struct ChildLogger<'a> {
+    logger: &'a Logger,
+    fields: Vec<(String, String)>,
+}
+
+impl<'a> ChildLogger<'a> {
+    // Bind a field, replacing a bound field with the same key
+    pub fn bind(mut self, key: &str, value: &str) -> Self {
+        self.fields.retain(|(field_key, _)| field_key != key);
+        self.fields.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    // Get a child starting with a copy of these bound fields
+    pub fn child(&self) -> ChildLogger<'a> {
+        ChildLogger {
+            logger: self.logger,
+            fields: self.fields.clone(),
+        }
+    }
+
+    // Time a block of work like Logger::span, with the bound fields added to the span's messages
+    pub fn span(&self, key: &str) -> LogSpan<'a> {
+        LogSpan::new(self.logger, key, self.fields.clone())
+    }
+
+    // Log with the bound fields followed by the given ones (which win on duplicate keys)
+    pub fn log_with(&self, level: LogLevel, key: &str, value: &str, fields: &[(&str, &str)], color: LogColor) {
+        let merged: Vec<(&str, &str)> = self
+            .fields
+            .iter()
+            .filter(|(bound_key, _)| !fields.iter().any(|(field_key, _)| field_key == bound_key))
+            .map(|(bound_key, bound_value)| (bound_key.as_str(), bound_value.as_str()))
+            .chain(fields.iter().copied())
+            .collect();
+        self.logger.log_with(level, key, value, &merged, color);
+    }
+
+    pub fn info(&self, key: &str, value: &str, color: LogColor) {
+        self.log_with(LogLevel::Info, key, value, &[], color);
+    }
+
+    pub fn warn(&self, key: &str, value: &str, color: LogColor) {
+        self.log_with(LogLevel::Warn, key, value, &[], color);
+    }
+
+    pub fn error(&self, key: &str, value: &str, color: LogColor) {
+        self.log_with(LogLevel::Error, key, value, &[], color);
+    }
+
+    pub fn debug(&self, key: &str, value: &str, color: LogColor) {
+        self.log_with(LogLevel::Debug, key, value, &[], color);
+    }
+
+    pub fn critical(&self, key: &str, value: &str, color: LogColor) {
+        self.log_with(LogLevel::Critical, key, value, &[], color);
+    }
+
+    pub fn fatal(&self, key: &str, value: &str, color: LogColor) {
+        self.log_with(LogLevel::Fatal, key, value, &[], color);
+    }
+
+    pub fn trace(&self, key: &str, value: &str, color: LogColor) {
+        self.log_with(LogLevel::Trace, key, value, &[], color);
+    }
+}
+
+// Builder to configure and initialize a Logger in one expression; each option forwards to the Logger setter of the same name
+pub struct LoggerBuilder {
+    logger: Logger,
+    file_path: Option<String>,
+    filter_from_env: bool,
+}
+
+impl LoggerBuilder {
+    // Create a builder starting from Logger::new()
+    pub fn new() -> Self {
+        LoggerBuilder {
+            logger: Logger::new(),
+            file_path: None,
+            filter_from_env: false,
+        }
+    }
+
+    // Set color enabled or disabled
+    pub fn color(mut self, color_enabled: bool) -> Self {
+        self.logger.set_color_enabled(color_enabled);
+        self
+    }
+
+    pub fn force_color(mut self, force_color: bool) -> Self {
+        self.logger.set_force_color(force_color);
+        self
+    }
+
+    pub fn level_style(mut self, level: LogLevel, style: LevelStyle) -> Self {
+        self.logger.set_level_style(level, style);
+        self
+    }
+
+    pub fn field_colors(mut self, key_color: Option<LogColor>, value_color: Option<LogColor>) -> Self {
+        self.logger.set_field_colors(key_color, value_color);
+        self
+    }
+
+    // Store the log messages in the given file (opened on build)
+    pub fn file_sink(mut self, file_path: &str) -> Self {
+        self.file_path = Some(file_path.to_string());
+        self
+    }
+
+    // Set default file path
+    pub fn default_file_path(mut self, path: &str) -> Self {
+        self.logger.set_default_file_path(path);
+        self
+    }
+
+    // Set default max file size
+    pub fn default_max_file_size(mut self, max_size: u64) -> Self {
+        self.logger.set_default_max_file_size(max_size);
+        self
+    }
+
+    // Limit the message value to max_len bytes
+    pub fn max_message_len(mut self, max_len: usize) -> Self {
+        self.logger.set_max_message_len(Some(max_len));
+        self
+    }
+
+    // Set compact output
+    pub fn compact(mut self, compact: bool) -> Self {
+        self.logger.set_compact(compact);
+        self
+    }
+
+    pub fn pad_level(mut self, pad_level: bool) -> Self {
+        self.logger.set_pad_level(pad_level);
+        self
+    }
+
+    pub fn interpolate_message(mut self, interpolate_message: bool) -> Self {
+        self.logger.set_interpolate_message(interpolate_message);
+        self
+    }
+
+    pub fn console_enabled(mut self, enabled: bool) -> Self {
+        self.logger.set_console_enabled(enabled);
+        self
+    }
+
+    pub fn file_enabled(mut self, enabled: bool) -> Self {
+        self.logger.set_file_enabled(enabled);
+        self
+    }
+
+    pub fn console_target(mut self, target: ConsoleTarget) -> Self {
+        self.logger.set_console_target(target);
+        self
+    }
+
+    pub fn level_console_target(mut self, level: LogLevel, target: ConsoleTarget) -> Self {
+        self.logger.set_level_console_target(level, target);
+        self
+    }
+
+    pub fn console_level(mut self, level: LogLevel, enabled: bool) -> Self {
+        self.logger.set_console_level(level, enabled);
+        self
+    }
+
+    pub fn storage_level(mut self, level: LogLevel, enabled: bool) -> Self {
+        self.logger.set_storage_level(level, enabled);
+        self
+    }
+
+    pub fn multiline_policy(mut self, policy: MultilinePolicy) -> Self {
+        self.logger.set_multiline_policy(policy);
+        self
+    }
+
+    pub fn console_multiline_policy(mut self, policy: MultilinePolicy) -> Self {
+        self.logger.set_console_multiline_policy(policy);
+        self
+    }
+
+    pub fn line_ending(mut self, line_ending: LineEnding) -> Self {
+        self.logger.set_line_ending(line_ending);
+        self
+    }
+
+    pub fn console_line_ending(mut self, line_ending: LineEnding) -> Self {
+        self.logger.set_console_line_ending(line_ending);
+        self
+    }
+
+    pub fn file_error_action(mut self, action: FileErrorAction) -> Self {
+        self.logger.set_file_error_action(action);
+        self
+    }
+
+    // Permission bits for the log file if it is created; ignored off Unix
+    pub fn file_mode(mut self, mode: u32) -> Self {
+        self.logger.set_file_mode(Some(mode));
+        self
+    }
+
+    // Read filter directives from LOGLY_FILTER or RUST_LOG on build
+    pub fn filter_from_env(mut self, filter_from_env: bool) -> Self {
+        self.filter_from_env = filter_from_env;
+        self
+    }
+
+    // Add a "host" field to every record
+    pub fn include_host(mut self, include_host: bool) -> Self {
+        self.logger.set_include_host(include_host);
+        self
+    }
+
+    // Add a "pid" field to every record
+    pub fn include_pid(mut self, include_pid: bool) -> Self {
+        self.logger.set_include_pid(include_pid);
+        self
+    }
+
+    // Build the Logger, opening the log file if one was given
+    pub fn build(self) -> std::io::Result<Logger> {
+        let mut logger = self.logger;
+        if self.filter_from_env {
+            logger
+                .set_filter_directives_from_env()
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+        }
+
+        if let Some(file_path) = self.file_path {
+            logger.start_logging(&file_path)?;
+        }
+
+        Ok(logger)
+    }
+}
+
+impl Default for LoggerBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,99 @@
+// sampled.rs
+
+// Each macro expansion owns a static, so the once / every-n state is per call site and shared by all threads.
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __log_sampled_color {
+    ($level:ident) => {
+        $crate::logly::LogLevel::$level.default_color()
+    };
+    ($level:ident, $color:expr) => {
+        $color
+    };
+}
+
+// Log only the first time this call site is reached: `log_once!(logger, Info, key, value [, color])`
+#[macro_export]
+macro_rules! log_once {
+    ($logger:expr, $level:ident, $key:expr, $value:expr $(, $color:expr)? $(,)?) => {{
+        static ONCE: ::std::sync::Once = ::std::sync::Once::new();
+        ONCE.call_once(|| {
+            $logger.log_with(
+                $crate::logly::LogLevel::$level,
+                $key,
+                $value,
+                &[],
+                $crate::__log_sampled_color!($level $(, $color)?),
+            )
+        });
+    }};
+}
+
+// Log the 1st, (n+1)th, (2n+1)th... time this call site is reached: `log_every_n!(logger, n, Info, key, value [, color])`
+#[macro_export]
+macro_rules! log_every_n {
+    ($logger:expr, $n:expr, $level:ident, $key:expr, $value:expr $(, $color:expr)? $(,)?) => {{
+        static CALLS: ::std::sync::atomic::AtomicU64 = ::std::sync::atomic::AtomicU64::new(0);
+        let n: u64 = $n;
+        if CALLS.fetch_add(1, ::std::sync::atomic::Ordering::Relaxed) % n.max(1) == 0 {
+            $logger.log_with(
+                $crate::logly::LogLevel::$level,
+                $key,
+                $value,
+                &[],
+                $crate::__log_sampled_color!($level $(, $color)?),
+            );
+        }
+    }};
+}
+
+#[macro_export]
+macro_rules! info_once {
+    ($logger:expr, $($arg:tt)+) => { $crate::log_once!($logger, Info, $($arg)+) };
+}
+
+#[macro_export]
+macro_rules! warn_once {
+    ($logger:expr, $($arg:tt)+) => { $crate::log_once!($logger, Warn, $($arg)+) };
+}
+
+#[macro_export]
+macro_rules! error_once {
+    ($logger:expr, $($arg:tt)+) => { $crate::log_once!($logger, Error, $($arg)+) };
+}
+
+#[macro_export]
+macro_rules! debug_once {
+    ($logger:expr, $($arg:tt)+) => { $crate::log_once!($logger, Debug, $($arg)+) };
+}
+
+#[macro_export]
+macro_rules! trace_once {
+    ($logger:expr, $($arg:tt)+) => { $crate::log_once!($logger, Trace, $($arg)+) };
+}
+
+#[macro_export]
+macro_rules! info_every_n {
+    ($logger:expr, $n:expr, $($arg:tt)+) => { $crate::log_every_n!($logger, $n, Info, $($arg)+) };
+}
+
+#[macro_export]
+macro_rules! warn_every_n {
+    ($logger:expr, $n:expr, $($arg:tt)+) => { $crate::log_every_n!($logger, $n, Warn, $($arg)+) };
+}
+
+#[macro_export]
+macro_rules! error_every_n {
+    ($logger:expr, $n:expr, $($arg:tt)+) => { $crate::log_every_n!($logger, $n, Error, $($arg)+) };
+}
+
+#[macro_export]
+macro_rules! debug_every_n {
+    ($logger:expr, $n:expr, $($arg:tt)+) => { $crate::log_every_n!($logger, $n, Debug, $($arg)+) };
+}
+
+#[macro_export]
+macro_rules! trace_every_n {
+    ($logger:expr, $n:expr, $($arg:tt)+) => { $crate::log_every_n!($logger, $n, Trace, $($arg)+) };
+}
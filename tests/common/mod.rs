@@ -0,0 +1,61 @@
+// logly/tests/common/mod.rs
+// Helpers shared by the test binaries (each uses only some of them)
+#![allow(dead_code)]
+
+use logly::logly::*;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+// Custom sink collecting records in memory as "level key value k=v,..." (and their colors)
+#[derive(Clone, Default)]
+pub struct MemorySink {
+    pub records: Arc<Mutex<Vec<String>>>,
+    pub colors: Arc<Mutex<Vec<LogColor>>>,
+}
+
+impl LogSink for MemorySink {
+    fn write(&self, record: &LogRecord) -> std::io::Result<()> {
+        let fields: Vec<String> = record.fields.iter().map(|(key, value)| format!("{}={}", key, value)).collect();
+        self.records
+            .lock()
+            .unwrap()
+            .push(format!("{} {} {} {}", record.level, record.key, record.value, fields.join(",")));
+        self.colors.lock().unwrap().push(record.color);
+        Ok(())
+    }
+}
+
+// Log file path unique to this process and call, removed when dropped
+pub struct TempLogFile {
+    path: PathBuf,
+}
+
+impl TempLogFile {
+    pub fn new(name: &str) -> Self {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let unique = format!("logly_{}_{}_{}.txt", name, std::process::id(), NEXT.fetch_add(1, Ordering::Relaxed));
+        let path = std::env::temp_dir().join(unique);
+        let _ = std::fs::remove_file(&path);
+        TempLogFile { path }
+    }
+
+    pub fn path(&self) -> &str {
+        self.path.to_str().unwrap()
+    }
+
+    pub fn read(&self) -> String {
+        std::fs::read_to_string(&self.path).expect("Error reading log file")
+    }
+}
+
+impl Drop for TempLogFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+// Uncolored Logger writing to the given file
+pub fn file_logger(path: &str) -> Logger {
+    Logger::builder().color(false).file_sink(path).build().expect("Error building logger")
+}
@@ -0,0 +1,85 @@
+// logly/tests/formatters_tests.rs
+
+use logly::formatters::*;
+use logly::logly::*;
+use std::borrow::Cow;
+
+fn record<'a>(level: LogLevel, key: &'a str, value: &'a str, fields: &[(&'a str, &'a str)]) -> LogRecord<'a> {
+    LogRecord {
+        level,
+        key,
+        value: Cow::Borrowed(value),
+        fields: fields.iter().map(|&(key, value)| (Cow::Borrowed(key), Cow::Borrowed(value))).collect(),
+        color: level.default_color(),
+    }
+}
+
+#[test]
+pub fn test_gelf_required_keys_and_additional_fields() {
+    let formatter = GelfFormatter::with_host("web-1");
+    let gelf = formatter.format(&record(
+        LogLevel::Error,
+        "db",
+        "query \"users\" failed",
+        &[("request_id", "42"), ("id", "7"), ("user name", "alice")],
+    ));
+
+    assert!(gelf.starts_with("{\"version\":\"1.1\",\"host\":\"web-1\",\"short_message\":\"query \\\"users\\\" failed\",\"timestamp\":"));
+    assert!(gelf.contains(",\"level\":3,\"_key\":\"db\",\"_request_id\":\"42\",\"_id_\":\"7\",\"_user_name\":\"alice\"}"));
+
+    let timestamp = gelf.split("\"timestamp\":").nth(1).unwrap().split(',').next().unwrap();
+    let (seconds, fraction) = timestamp.split_once('.').unwrap();
+    assert!(seconds.parse::<u64>().unwrap() > 1_600_000_000);
+    assert_eq!(fraction.len(), 6);
+}
+
+#[test]
+pub fn test_gelf_key_field_does_not_collide_with_record_key() {
+    let gelf = GelfFormatter::with_host("web-1").format(&record(LogLevel::Info, "db", "ok", &[("key", "k1")]));
+
+    assert!(gelf.ends_with(",\"_key\":\"db\",\"_key_\":\"k1\"}"));
+    assert_eq!(gelf.matches("\"_key\":").count(), 1);
+}
+
+#[test]
+pub fn test_gelf_defaults_to_system_hostname() {
+    let gelf = GelfFormatter::new().format(&record(LogLevel::Info, "Key1", "Value1", &[]));
+
+    assert!(!gelf.contains("\"host\":\"\""));
+    assert!(gelf.contains(",\"level\":6,"));
+}
+
+#[test]
+pub fn test_cef_header_assembly() {
+    let formatter = CefFormatter::new("Acme", "Portal", "1.2");
+    let cef = formatter.format(&record(LogLevel::Critical, "auth-100", "Login failed", &[("src", "10.0.0.1"), ("suser", "alice")]));
+
+    assert_eq!(cef, "CEF:0|Acme|Portal|1.2|auth-100|Login failed|9|src=10.0.0.1 suser=alice");
+}
+
+#[test]
+pub fn test_cef_skips_fields_without_a_usable_key() {
+    let formatter = CefFormatter::new("Acme", "Portal", "1.2");
+    let cef = formatter.format(&record(LogLevel::Info, "auth", "ok", &[("-", "dash"), ("é", "accent"), ("src", "10.0.0.1")]));
+
+    assert_eq!(cef, "CEF:0|Acme|Portal|1.2|auth|ok|3|src=10.0.0.1");
+
+    let cef = formatter.format(&record(LogLevel::Info, "auth", "ok", &[("-", "dash")]));
+    assert_eq!(cef, "CEF:0|Acme|Portal|1.2|auth|ok|3|");
+}
+
+#[test]
+pub fn test_cef_escaping() {
+    let formatter = CefFormatter::new("Ac|me", "Portal", "1.2");
+    let cef = formatter.format(&record(
+        LogLevel::Info,
+        "auth",
+        "a|b\\c",
+        &[("query", "a=b"), ("path", "C:\\temp"), ("note", "line1\nline2"), ("bad key", "x")],
+    ));
+
+    assert_eq!(
+        cef,
+        "CEF:0|Ac\\|me|Portal|1.2|auth|a\\|b\\\\c|3|query=a\\=b path=C:\\\\temp note=line1\\nline2 badkey=x"
+    );
+}
@@ -0,0 +1,29 @@
+// logly/tests/global_tests.rs
+
+mod common;
+
+use common::MemorySink;
+use logly::global::{GlobalLoggerAlreadySet, NoGlobalLogger};
+use logly::logly::*;
+
+#[test]
+pub fn test_global_logger_macros() {
+    assert!(logly::global().is_none());
+    assert_eq!(logly::info!("Key1", "Value1"), Err(NoGlobalLogger));
+
+    let sink = MemorySink::default();
+    let mut logly = Logger::new();
+    logly.set_console_enabled(false);
+    logly.add_custom_sink(Box::new(sink.clone()));
+    assert!(logly::set_global(logly).is_ok());
+    assert_eq!(logly::set_global(Logger::new()), Err(GlobalLoggerAlreadySet));
+
+    logly::info!("Key1", "Value1").unwrap();
+    logly::error!("Key2", "Value2", LogColor::White).unwrap();
+
+    assert_eq!(
+        *sink.records.lock().unwrap(),
+        vec!["Info Key1 Value1 ".to_string(), "Error Key2 Value2 ".to_string()]
+    );
+    assert_eq!(*sink.colors.lock().unwrap(), vec![LogColor::Cyan, LogColor::White]);
+}
@@ -0,0 +1,1224 @@
+// logly/tests/integration_tests.rs
+
+mod common;
+
+use common::{file_logger, MemorySink, TempLogFile};
+use logly::logly::*;
+use std::io::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+
+// Custom sink failing every write
+struct FailingSink;
+
+impl LogSink for FailingSink {
+    fn write(&self, _record: &LogRecord) -> std::io::Result<()> {
+        Err(std::io::Error::other("sink unavailable"))
+    }
+}
+
+// Custom sink reporting every record through the Logger it belongs to
+struct ReentrantSink {
+    logger: Arc<OnceLock<&'static Logger>>,
+    writes: Arc<AtomicUsize>,
+}
+
+impl LogSink for ReentrantSink {
+    fn write(&self, _record: &LogRecord) -> std::io::Result<()> {
+        self.writes.fetch_add(1, Ordering::SeqCst);
+        if let Some(logger) = self.logger.get() {
+            logger.warn("sink", "nested", LogColor::Yellow);
+        }
+        Ok(())
+    }
+}
+
+// Custom formatter logging a note through the Logger it belongs to for every "Key1" record
+struct ReentrantFormatter {
+    logger: Arc<OnceLock<&'static Logger>>,
+}
+
+impl RecordFormatter for ReentrantFormatter {
+    fn format(&self, record: &LogRecord) -> String {
+        if record.key == "Key1" {
+            if let Some(logger) = self.logger.get() {
+                logger.info("formatter", "formatting", LogColor::Cyan);
+            }
+        }
+        format!("{} {}", record.key, record.value)
+    }
+}
+
+#[test]
+pub fn test_start_and_stop_logging() {
+    let logly = logly::new();
+
+    assert!(logly.start_logging("test_log.txt").is_ok());
+
+    logly.info("Key1", "Value1", LogColor::Cyan);
+    logly.warn("Key2", "Value2", LogColor::Yellow);
+
+    logly.stop_logging();
+
+    // Assert that the log file was created and contains expected content
+    let content = std::fs::read_to_string("test_log.txt").expect("Error reading log file");
+    assert!(content.contains("Key1"));
+    assert!(content.contains("Value1"));
+    assert!(content.contains("Key2"));
+    assert!(content.contains("Value2"));
+}
+
+
+#[test]
+pub fn test_builder_with_file_sink() {
+    let file = TempLogFile::new("builder");
+    let path = file.path();
+
+    let logly = Logger::builder()
+        .color(false)
+        .file_sink(path)
+        .build()
+        .expect("Error building logger");
+
+    logly.info("Key1", "Value1", LogColor::Cyan);
+    logly.stop_logging();
+
+    let content = file.read();
+    assert_eq!(content, "[Info]: Key1 - Value1\n");
+}
+
+#[test]
+pub fn test_builder_forwards_to_setters() {
+    assert_eq!(Logger::builder().build().unwrap().config_snapshot(), Logger::new().config_snapshot());
+
+    let built = Logger::builder()
+        .pad_level(true)
+        .console_target(ConsoleTarget::Stderr)
+        .line_ending(LineEnding::CrLf)
+        .field_colors(Some(LogColor::Blue), None)
+        .build()
+        .unwrap();
+
+    let mut configured = Logger::new();
+    configured.set_pad_level(true);
+    configured.set_console_target(ConsoleTarget::Stderr);
+    configured.set_line_ending(LineEnding::CrLf);
+    configured.set_field_colors(Some(LogColor::Blue), None);
+    assert_eq!(built.config_snapshot(), configured.config_snapshot());
+}
+
+#[test]
+pub fn test_builder_surfaces_file_errors() {
+    let dir = std::env::temp_dir();
+    let result = Logger::builder().file_sink(dir.to_str().unwrap()).build();
+
+    assert!(result.is_err());
+}
+
+#[test]
+pub fn test_start_logging_to_a_directory_fails() {
+    let dir = std::env::temp_dir();
+    let dir = dir.to_str().unwrap();
+
+    let err = Logger::new().start_logging(dir).unwrap_err();
+
+    assert_eq!(err.kind(), std::io::ErrorKind::IsADirectory);
+    assert!(err.to_string().contains(dir));
+    assert!(err.to_string().contains("is a directory"));
+}
+
+#[test]
+pub fn test_start_logging_without_parent_directory_fails() {
+    let path = std::env::temp_dir().join("logly_missing_dir").join("log.txt");
+
+    let err = Logger::new().start_logging(path.to_str().unwrap()).unwrap_err();
+
+    assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+    assert!(err.to_string().contains("parent directory does not exist"));
+}
+
+#[test]
+pub fn test_fields_attach_to_one_record() {
+    let file = TempLogFile::new("fields");
+    let path = file.path();
+
+    let logly = file_logger(path);
+
+    logly.info_with("request", "handled", &[("request_id", "42"), ("status", "200")], LogColor::Cyan);
+    logly.info("request", "next", LogColor::Cyan);
+    logly.stop_logging();
+
+    let content = file.read();
+    let lines: Vec<&str> = content.lines().collect();
+    assert_eq!(lines[0], "[Info]: request - handled request_id=42 status=200");
+    assert_eq!(lines[1], "[Info]: request - next");
+}
+
+#[test]
+pub fn test_redaction_masks_keys_and_values() {
+    let mut logly = Logger::new();
+    logly.set_color_enabled(false);
+    logly.add_redaction("(?i)^password$").unwrap();
+    logly.add_redaction_regex(r"\b(?:\d[ -]?){12}(\d{4})\b", "****-$1").unwrap();
+
+    let line = logly.format_record(LogLevel::Info, "password", "hunter2", LogColor::Cyan);
+    assert_eq!(line, "[Info]: password - ***\n");
+
+    let line = logly.format_record(LogLevel::Info, "payment", "card 4111 1111 1111 1234 ok", LogColor::Cyan);
+    assert_eq!(line, "[Info]: payment - card ****-1234 ok\n");
+}
+
+#[test]
+pub fn test_redaction_applies_to_fields() {
+    let file = TempLogFile::new("redaction");
+    let path = file.path();
+
+    let mut logly = file_logger(path);
+    logly.add_redaction("(?i)^password$").unwrap();
+
+    logly.info_with("login", "ok", &[("user", "alice"), ("Password", "hunter2")], LogColor::Cyan);
+    logly.stop_logging();
+
+    let content = file.read();
+    assert_eq!(content, "[Info]: login - ok user=alice Password=***\n");
+}
+
+#[test]
+pub fn test_max_message_len_truncates_value_only() {
+    let logly = Logger::builder().color(false).max_message_len(5).build().unwrap();
+
+    let line = logly.format_record(LogLevel::Info, "Key1", "0123456789", LogColor::Cyan);
+    assert_eq!(line, "[Info]: Key1 - 01234… (truncated 5 bytes)\n");
+
+    let line = logly.format_record(LogLevel::Info, "Key1", "01234", LogColor::Cyan);
+    assert_eq!(line, "[Info]: Key1 - 01234\n");
+}
+
+#[test]
+pub fn test_max_message_len_respects_char_boundaries() {
+    let logly = Logger::builder().color(false).max_message_len(5).build().unwrap();
+
+    let line = logly.format_record(LogLevel::Info, "Key1", "héllo", LogColor::Cyan);
+    assert_eq!(line, "[Info]: Key1 - héll… (truncated 1 bytes)\n");
+
+    // A 5-byte limit falls inside "本", so the cut backs off to the end of "日"
+    let line = logly.format_record(LogLevel::Info, "Key1", "日本", LogColor::Cyan);
+    assert_eq!(line, "[Info]: Key1 - 日… (truncated 3 bytes)\n");
+}
+
+#[test]
+pub fn test_trace_context_is_added_to_records() {
+    let mut logly = Logger::new();
+    logly.set_color_enabled(false);
+    logly.add_context_provider(Box::new(TraceContext));
+
+    let line = logly.format_record(LogLevel::Info, "Key1", "Value1", LogColor::Cyan);
+    assert_eq!(line, "[Info]: Key1 - Value1\n");
+
+    Logger::set_trace_context("4bf92f35", "00f067aa");
+    let line = logly.format_record(LogLevel::Info, "Key1", "Value1", LogColor::Cyan);
+    assert_eq!(line, "[Info]: Key1 - Value1 trace_id=4bf92f35 span_id=00f067aa\n");
+
+    Logger::clear_trace_context();
+    let line = logly.format_record(LogLevel::Info, "Key1", "Value1", LogColor::Cyan);
+    assert_eq!(line, "[Info]: Key1 - Value1\n");
+}
+
+#[test]
+pub fn test_host_and_pid_fields() {
+    let mut logly = Logger::builder().color(false).include_host(true).include_pid(true).build().unwrap();
+
+    let line = logly.format_record(LogLevel::Info, "Key1", "Value1", LogColor::Cyan);
+    assert!(line.starts_with("[Info]: Key1 - Value1 host="));
+    assert!(line.ends_with(&format!(" pid={}\n", std::process::id())));
+
+    logly.set_include_host(false);
+    logly.set_include_pid(false);
+    let line = logly.format_record(LogLevel::Info, "Key1", "Value1", LogColor::Cyan);
+    assert_eq!(line, "[Info]: Key1 - Value1\n");
+}
+
+#[test]
+pub fn test_padded_levels_align() {
+    let mut logly = Logger::new();
+    logly.set_pad_level(true);
+
+    let line = logly.format_record(LogLevel::Info, "Key1", "Value1", LogColor::Cyan);
+    assert_eq!(line, "\x1b[36m[Info    ]: Key1 - Value1\x1b[0m\x1b[0m\n");
+
+    let escape_codes = regex::Regex::new("\x1b\\[[0-9;]*m").unwrap();
+    let visible: Vec<String> = LogLevel::ALL
+        .iter()
+        .map(|&level| {
+            let line = logly.format_record(level, "Key1", "Value1", LogColor::Rgb(255, 136, 0));
+            escape_codes.replace_all(&line, "").into_owned()
+        })
+        .collect();
+    assert!(visible.iter().all(|line| line.find("]:") == Some(9)), "{:?}", visible);
+}
+
+#[test]
+pub fn test_message_interpolation() {
+    let sink = MemorySink::default();
+
+    let mut logly = Logger::new();
+    logly.set_console_enabled(false);
+    logly.add_redaction("^token$").unwrap();
+    logly.add_custom_sink(Box::new(sink.clone()));
+
+    let message = "user {user_id} logged in {token} {missing} {}";
+    let fields = [("user_id", "42"), ("token", "abc")];
+    logly.info_with("auth", message, &fields, LogColor::Cyan);
+    logly.set_interpolate_message(true);
+    logly.info_with("auth", message, &fields, LogColor::Cyan);
+
+    assert_eq!(
+        *sink.records.lock().unwrap(),
+        vec![
+            "Info auth user {user_id} logged in {token} {missing} {} user_id=42,token=***".to_string(),
+            "Info auth user 42 logged in *** {missing} {} user_id=42,token=***".to_string(),
+        ]
+    );
+}
+
+#[test]
+pub fn test_config_snapshot() {
+    let mut logly = Logger::builder().color(false).compact(true).max_message_len(64).include_pid(true).build().unwrap();
+    logly.set_storage_level(LogLevel::Debug, false);
+    logly.set_console_target(ConsoleTarget::Stderr);
+    logly.set_field_colors(None, Some(LogColor::Blue));
+    logly.set_level_style(LogLevel::Error, LevelStyle { bold: true, ..LevelStyle::default() });
+
+    let config = logly.config_snapshot();
+    assert!(!config.color_enabled);
+    assert!(config.compact);
+    assert_eq!(config.max_message_len, Some(64));
+    assert!(config.include_pid);
+    assert!(!config.include_host);
+    assert_eq!(config.storage_levels.get(&LogLevel::Debug), Some(&false));
+    assert_eq!(config.console_target, ConsoleTarget::Stderr);
+    assert_eq!(config.log_file, None);
+    assert_eq!(config.line_ending, LineEnding::Lf);
+    assert_eq!(config.field_key_color, None);
+    assert_eq!(config.field_value_color, Some(LogColor::Blue));
+    assert!(config.level_styles[&LogLevel::Error].bold);
+    assert!(!config.level_styles.contains_key(&LogLevel::Info));
+}
+
+#[test]
+pub fn test_stats_count_records_per_level() {
+    let logly = Logger::builder().color(false).build().unwrap();
+
+    logly.info("Key1", "Value1", LogColor::Cyan);
+    logly.info("Key2", "Value2", LogColor::Cyan);
+    logly.info("Key3", "Value3", LogColor::Cyan);
+    logly.error("Key4", "Value4", LogColor::Red);
+
+    let stats = logly.stats();
+    assert_eq!(stats.records(LogLevel::Info), 3);
+    assert_eq!(stats.records(LogLevel::Error), 1);
+    assert_eq!(stats.records(LogLevel::Warn), 0);
+    assert_eq!(stats.total_records, 4);
+    assert_eq!(stats.write_errors, 0);
+
+    logly.reset_stats();
+    assert_eq!(logly.stats().total_records, 0);
+}
+
+#[cfg(unix)]
+#[test]
+pub fn test_log_file_mode() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let file = TempLogFile::new("file_mode");
+
+    let logly = Logger::builder().file_mode(0o600).file_sink(file.path()).build().unwrap();
+    logly.stop_logging();
+
+    let mode = std::fs::metadata(file.path()).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+pub fn test_stats_count_write_errors() {
+    let logly = Logger::builder().color(false).file_sink("/dev/full").build().unwrap();
+
+    logly.info("Key1", "Value1", LogColor::Cyan);
+
+    assert_eq!(logly.stats().write_errors, 1);
+    assert_eq!(logly.stats().fallback_records, 0);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+pub fn test_file_error_fallback_to_stderr() {
+    if std::env::var_os("LOGLY_FALLBACK_CHILD").is_some() {
+        let mut logly = Logger::builder().color(false).file_sink("/dev/full").build().unwrap();
+        logly.set_console_enabled(false);
+        logly.set_file_error_action(FileErrorAction::Fallback);
+
+        logly.info("Key1", "Value1", LogColor::Cyan);
+        logly.info("Key2", "Value2", LogColor::Cyan);
+
+        let stats = logly.stats();
+        assert_eq!(stats.write_errors, 2);
+        assert_eq!(stats.fallback_records, 2);
+        return;
+    }
+
+    // Run in a child process, as libtest does not capture direct writes to stderr
+    let output = std::process::Command::new(std::env::current_exe().unwrap())
+        .args(["--exact", "test_file_error_fallback_to_stderr", "--nocapture", "--test-threads=1"])
+        .env("LOGLY_FALLBACK_CHILD", "1")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let lines: Vec<&str> = stderr.lines().collect();
+    assert_eq!(
+        lines,
+        vec![
+            "Error writing to log file: No space left on device (os error 28)",
+            "[Info]: Key1 - Value1",
+            "Error writing to log file: No space left on device (os error 28)",
+            "[Info]: Key2 - Value2",
+        ]
+    );
+}
+
+#[test]
+pub fn test_per_level_console_and_storage_routing() {
+    let file = TempLogFile::new("routing");
+    let path = file.path();
+
+    let mut logly = file_logger(path);
+    logly.set_console_level(LogLevel::Debug, false);
+    logly.set_storage_level(LogLevel::Trace, false);
+
+    logly.debug("Key1", "file only", LogColor::Blue);
+    logly.trace("Key2", "console only", LogColor::Blue);
+    logly.error("Key3", "both", LogColor::Red);
+    logly.stop_logging();
+
+    let content = file.read();
+    assert_eq!(content, "[Debug]: Key1 - file only\n[Error]: Key3 - both\n");
+}
+
+#[test]
+pub fn test_compact_output_is_shorter() {
+    let normal = Logger::builder().color(false).build().unwrap();
+    let compact = Logger::builder().color(false).compact(true).build().unwrap();
+
+    let normal_line = normal.format_record(LogLevel::Warn, "disk", "90% full", LogColor::Yellow);
+    let compact_line = compact.format_record(LogLevel::Warn, "disk", "90% full", LogColor::Yellow);
+
+    assert_eq!(compact_line, "W disk=90% full\n");
+    assert!(compact_line.len() < normal_line.len());
+}
+
+#[test]
+pub fn test_field_colors() {
+    let file = TempLogFile::new("field_colors");
+    let path = file.path();
+
+    let mut logly = Logger::builder().file_sink(path).build().unwrap();
+    logly.set_field_colors(Some(LogColor::White), Some(LogColor::Yellow));
+
+    logly.info_with("Key1", "Value1", &[("user", "alice")], LogColor::Cyan);
+    logly.set_color_enabled(false);
+    logly.info_with("Key2", "Value2", &[("user", "bob")], LogColor::Cyan);
+    logly.stop_logging();
+
+    let content = file.read();
+    let lines: Vec<&str> = content.lines().collect();
+    assert_eq!(
+        lines[0],
+        "\x1b[36m[Info]: Key1 - Value1 \x1b[37muser\x1b[0m\x1b[36m=\x1b[33malice\x1b[0m\x1b[36m\x1b[0m\x1b[0m"
+    );
+    assert_eq!(lines[1], "[Info]: Key2 - Value2 user=bob");
+}
+
+#[test]
+pub fn test_console_color_detection() {
+    let terminal = ColorEnvironment {
+        no_color: false,
+        clicolor_force: false,
+        stdout_is_terminal: true,
+        stderr_is_terminal: false,
+    };
+    let mut logly = Logger::new();
+    logly.set_color_environment(terminal);
+    let console_line = |logly: &Logger| logly.format_console_record(LogLevel::Info, "Key1", "Value1", LogColor::Cyan);
+
+    assert!(logly.console_colors_enabled());
+    assert!(console_line(&logly).starts_with("\x1b[36m"));
+
+    // A piped stream is uncolored unless CLICOLOR_FORCE is set
+    logly.set_console_target(ConsoleTarget::Stderr);
+    assert_eq!(console_line(&logly), "[Info]: Key1 - Value1\n");
+    logly.set_color_environment(ColorEnvironment { clicolor_force: true, ..terminal });
+    assert!(console_line(&logly).contains("\x1b["));
+    logly.set_console_target(ConsoleTarget::Stdout);
+
+    // With NO_COLOR set the console is uncolored unless forced
+    logly.set_color_environment(ColorEnvironment { no_color: true, ..terminal });
+    assert!(!logly.console_colors_enabled());
+    assert!(!console_line(&logly).contains("\x1b["));
+
+    logly.set_force_color(true);
+    assert!(console_line(&logly).contains("\x1b["));
+
+    logly.set_color_enabled(false);
+    assert!(!console_line(&logly).contains("\x1b["));
+
+    // The log file keeps following color_enabled only
+    logly.set_color_enabled(true);
+    logly.set_force_color(false);
+    let line = logly.format_record(LogLevel::Info, "Key1", "Value1", LogColor::Cyan);
+    assert!(line.starts_with("\x1b[36m"));
+}
+
+#[test]
+pub fn test_parse_color_specs() {
+    assert_eq!("#ff8800".parse::<LogColor>(), Ok(LogColor::Rgb(255, 136, 0)));
+    assert_eq!("rgb(255, 136, 0)".parse::<LogColor>(), Ok(LogColor::Rgb(255, 136, 0)));
+    assert_eq!("208".parse::<LogColor>(), Ok(LogColor::Ansi256(208)));
+    assert_eq!("Cyan".parse::<LogColor>(), Ok(LogColor::Cyan));
+
+    assert!("#ff88".parse::<LogColor>().is_err());
+    assert!("#+f+f+f".parse::<LogColor>().is_err());
+    assert!("rgb(+1,0,0)".parse::<LogColor>().is_err());
+    assert!("+208".parse::<LogColor>().is_err());
+    assert!("rgb(256,0,0)".parse::<LogColor>().is_err());
+    assert!("orange".parse::<LogColor>().is_err());
+}
+
+#[test]
+pub fn test_parse_level_names() {
+    for level in LogLevel::ALL {
+        assert_eq!(level.to_string().parse::<LogLevel>(), Ok(level));
+        assert_eq!(level.to_string().to_uppercase().parse::<LogLevel>(), Ok(level));
+    }
+    assert_eq!("warning".parse::<LogLevel>(), Ok(LogLevel::Warn));
+    assert!("verbose".parse::<LogLevel>().is_err());
+}
+
+#[test]
+pub fn test_truecolor_and_256_color_escapes() {
+    let logly = Logger::new();
+
+    let color = "#ff8800".parse::<LogColor>().unwrap();
+    let line = logly.format_record(LogLevel::Info, "Key1", "Value1", color);
+    assert_eq!(line, "\x1b[38;2;255;136;0m[Info]: Key1 - Value1\x1b[0m\x1b[0m\n");
+
+    let line = logly.format_record(LogLevel::Info, "Key1", "Value1", LogColor::Ansi256(208));
+    assert_eq!(line, "\x1b[38;5;208m[Info]: Key1 - Value1\x1b[0m\x1b[0m\n");
+}
+
+#[test]
+pub fn test_level_styles() {
+    let mut logly = Logger::new();
+    logly.set_level_style(
+        LogLevel::Critical,
+        LevelStyle { bold: true, underline: true, ..LevelStyle::default() },
+    );
+    logly.set_level_style(
+        LogLevel::Fatal,
+        LevelStyle { color: Some(LogColor::White), background: Some(LogColor::Red), ..LevelStyle::default() },
+    );
+
+    let line = logly.format_record(LogLevel::Critical, "Key1", "Value1", LogColor::Red);
+    assert!(line.starts_with("\x1b[1;4;31m[Critical]"));
+
+    let line = logly.format_record(LogLevel::Fatal, "Key1", "Value1", LogColor::Red);
+    assert!(line.starts_with("\x1b[37;41m[Fatal]"));
+
+    let line = logly.format_record(LogLevel::Error, "Key1", "Value1", LogColor::Red);
+    assert!(line.starts_with("\x1b[31m[Error]"));
+}
+
+#[test]
+pub fn test_mute_file_output() {
+    let file = TempLogFile::new("mute");
+    let path = file.path();
+
+    let mut logly = file_logger(path);
+    logly.set_console_enabled(false);
+    assert!(!logly.is_console_enabled());
+
+    logly.info("Key1", "stored", LogColor::Cyan);
+    logly.set_file_enabled(false);
+    assert!(!logly.is_file_enabled());
+    logly.info("Key2", "muted", LogColor::Cyan);
+    logly.set_file_enabled(true);
+    logly.info("Key3", "stored again", LogColor::Cyan);
+    logly.stop_logging();
+
+    let content = file.read();
+    assert_eq!(content, "[Info]: Key1 - stored\n[Info]: Key3 - stored again\n");
+}
+
+#[test]
+pub fn test_console_targets() {
+    let mut logly = Logger::new();
+    assert_eq!(logly.console_target(LogLevel::Info), ConsoleTarget::Stdout);
+
+    logly.set_level_console_target(LogLevel::Error, ConsoleTarget::Stderr);
+    assert_eq!(logly.console_target(LogLevel::Error), ConsoleTarget::Stderr);
+    assert_eq!(logly.console_target(LogLevel::Info), ConsoleTarget::Stdout);
+
+    logly.set_console_target(ConsoleTarget::Stderr);
+    logly.set_level_console_target(LogLevel::Info, ConsoleTarget::Stdout);
+    assert_eq!(logly.console_target(LogLevel::Warn), ConsoleTarget::Stderr);
+    assert_eq!(logly.console_target(LogLevel::Info), ConsoleTarget::Stdout);
+}
+
+// Runs in a child process whose stdout and stderr share one file, so the console writes can be read back
+#[test]
+pub fn test_console_lines_are_not_interleaved() {
+    const THREADS: usize = 4;
+    const RECORDS: usize = 200;
+
+    if std::env::var_os("LOGLY_CONSOLE_CHILD").is_some() {
+        // End the "test ... " line libtest has started
+        println!();
+        let mut to_stdout = Logger::new();
+        to_stdout.set_color_enabled(false);
+        let mut to_stderr = Logger::new();
+        to_stderr.set_color_enabled(false);
+        to_stderr.set_console_target(ConsoleTarget::Stderr);
+
+        std::thread::scope(|scope| {
+            for thread in 0..THREADS {
+                let (to_stdout, to_stderr) = (&to_stdout, &to_stderr);
+                scope.spawn(move || {
+                    for record in 0..RECORDS {
+                        to_stdout.info(&format!("t{}", thread), &record.to_string(), LogColor::Cyan);
+                        to_stderr.warn(&format!("t{}", thread), &record.to_string(), LogColor::Yellow);
+                    }
+                });
+            }
+        });
+        return;
+    }
+
+    let file = TempLogFile::new("console_child");
+    let output = std::fs::File::create(file.path()).unwrap();
+    let status = std::process::Command::new(std::env::current_exe().unwrap())
+        .args(["--exact", "test_console_lines_are_not_interleaved", "--nocapture", "--test-threads=1"])
+        .env("LOGLY_CONSOLE_CHILD", "1")
+        .stdout(output.try_clone().unwrap())
+        .stderr(output)
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let content = file.read();
+    let line_pattern = regex::Regex::new(r"^\[(Info|Warn)\]: t\d - \d+$").unwrap();
+    let logged: Vec<&str> = content.lines().filter(|line| line.contains("]: t")).collect();
+    assert_eq!(logged.len(), 2 * THREADS * RECORDS);
+    for line in logged {
+        assert!(line_pattern.is_match(line), "garbled line: {:?}", line);
+    }
+}
+
+#[test]
+pub fn test_multiline_policies() {
+    let mut logly = Logger::new();
+    logly.set_color_enabled(false);
+
+    let line = logly.format_record(LogLevel::Error, "trace", "first\nsecond", LogColor::Red);
+    assert_eq!(line, "[Error]: trace - first\\nsecond\n");
+
+    logly.set_multiline_policy(MultilinePolicy::Indent);
+    let line = logly.format_record(LogLevel::Error, "trace", "first\nsecond", LogColor::Red);
+    assert_eq!(line, "[Error]: trace - first\n    second\n");
+
+    logly.set_multiline_policy(MultilinePolicy::Raw);
+    let line = logly.format_record(LogLevel::Error, "trace", "first\nsecond", LogColor::Red);
+    assert_eq!(line, "[Error]: trace - first\nsecond\n");
+}
+
+#[test]
+pub fn test_multiline_values_stay_on_one_file_line() {
+    let file = TempLogFile::new("multiline");
+    let path = file.path();
+
+    let logly = file_logger(path);
+    logly.info_with("payload", "{\n  \"id\": 1\n}", &[("note", "a\r\nb")], LogColor::Cyan);
+    logly.stop_logging();
+
+    let content = file.read();
+    assert_eq!(content, "[Info]: payload - {\\n  \"id\": 1\\n} note=a\\r\\nb\n");
+}
+
+#[test]
+pub fn test_line_endings() {
+    let file = TempLogFile::new("line_ending");
+    let path = file.path();
+
+    let mut logly = file_logger(path);
+    logly.set_console_enabled(false);
+    logly.info("Key1", "Value1", LogColor::Cyan);
+    logly.set_line_ending(LineEnding::CrLf);
+    logly.info("Key2", "Value2", LogColor::Cyan);
+    logly.set_compact(true);
+    logly.info("Key3", "Value3", LogColor::Cyan);
+    logly.set_line_ending(LineEnding::Platform);
+    logly.info("Key4", "Value4", LogColor::Cyan);
+    logly.stop_logging();
+
+    let platform = if cfg!(windows) { "\r\n" } else { "\n" };
+    let content = std::fs::read(path).expect("Error reading log file");
+    assert_eq!(
+        content,
+        format!("[Info]: Key1 - Value1\n[Info]: Key2 - Value2\r\nI Key3=Value3\r\nI Key4=Value4{}", platform).into_bytes()
+    );
+}
+
+#[test]
+pub fn test_writer_logs_one_message_per_line() {
+    let file = TempLogFile::new("writer");
+    let path = file.path();
+
+    let logly = file_logger(path);
+    {
+        let mut writer = logly.writer(LogLevel::Info, "child", LogColor::White);
+        write!(writer, "first line\nsecond ").unwrap();
+        writer.write_all(b"line\r\npartial").unwrap();
+        assert_eq!(logly.stats().records(LogLevel::Info), 2);
+    }
+    logly.stop_logging();
+
+    let content = file.read();
+    assert_eq!(
+        content,
+        "[Info]: child - first line\n[Info]: child - second line\n[Info]: child - partial\n"
+    );
+}
+
+#[test]
+pub fn test_diagnostics_report() {
+    let file = TempLogFile::new("diagnostics");
+    let path = file.path();
+
+    let mut logly = Logger::builder().file_sink(path).build().unwrap();
+    logly.add_redaction("secret-pattern").unwrap();
+    logly.set_storage_level(LogLevel::Trace, false);
+
+    let report = logly.diagnostics();
+    assert!(report.starts_with(&format!("logly {}", env!("CARGO_PKG_VERSION"))));
+    assert!(report.contains(&format!("log file: {} (enabled)", path)));
+    assert!(report.contains("levels off in file: Trace"));
+    assert!(report.contains("redaction rules: 1 key, 0 value"));
+    assert!(!report.contains("secret-pattern"));
+
+    logly.stop_logging();
+    assert!(logly.diagnostics().contains("log file: none"));
+}
+
+#[test]
+pub fn test_custom_sinks_receive_records() {
+    let sink = MemorySink::default();
+
+    let mut logly = Logger::new();
+    logly.set_console_enabled(false);
+    logly.add_redaction("^token$").unwrap();
+    logly.add_custom_sink(Box::new(sink.clone()));
+    logly.add_custom_sink(Box::new(FailingSink));
+
+    logly.info_with("login", "ok", &[("token", "abc"), ("user", "alice")], LogColor::Cyan);
+    logly.error("db", "down", LogColor::Red);
+    assert!(logly.flush().is_ok());
+
+    assert_eq!(
+        *sink.records.lock().unwrap(),
+        vec!["Info login ok token=***,user=alice".to_string(), "Error db down ".to_string()]
+    );
+    assert_eq!(logly.stats().write_errors, 2);
+}
+
+#[test]
+pub fn test_child_logger_bound_fields() {
+    let sink = MemorySink::default();
+
+    let mut logly = Logger::new();
+    logly.set_console_enabled(false);
+    logly.add_custom_sink(Box::new(sink.clone()));
+
+    let request = logly.child().bind("request_id", "r-1").bind("user", "alice");
+    let retry = request.child().bind("request_id", "r-2");
+
+    request.info("handler", "started", LogColor::Cyan);
+    retry.log_with(LogLevel::Warn, "handler", "retrying", &[("user", "bob")], LogColor::Yellow);
+    logly.info("server", "idle", LogColor::Cyan);
+
+    assert_eq!(
+        *sink.records.lock().unwrap(),
+        vec![
+            "Info handler started request_id=r-1,user=alice".to_string(),
+            "Warn handler retrying request_id=r-2,user=bob".to_string(),
+            "Info server idle ".to_string(),
+        ]
+    );
+}
+
+#[test]
+pub fn test_once_and_every_n_macros() {
+    let sink = MemorySink::default();
+
+    let mut logly = Logger::new();
+    logly.set_console_enabled(false);
+    logly.add_custom_sink(Box::new(sink.clone()));
+
+    for i in 0..10 {
+        logly::info_every_n!(logly, 3, "loop", &i.to_string());
+        logly::warn_once!(logly, "loop", "slow", LogColor::White);
+    }
+
+    assert_eq!(
+        *sink.records.lock().unwrap(),
+        vec![
+            "Info loop 0 ".to_string(),
+            "Warn loop slow ".to_string(),
+            "Info loop 3 ".to_string(),
+            "Info loop 6 ".to_string(),
+            "Info loop 9 ".to_string(),
+        ]
+    );
+}
+
+#[test]
+pub fn test_transactions_are_not_interleaved() {
+    let sink = MemorySink::default();
+
+    let mut logly = Logger::new();
+    logly.set_console_enabled(false);
+    logly.add_custom_sink(Box::new(sink.clone()));
+    let logly = &logly;
+
+    std::thread::scope(|scope| {
+        for worker in ["a", "b"] {
+            scope.spawn(move || {
+                let mut transaction = logly.transaction();
+                for step in 0..5 {
+                    transaction.info(worker, &step.to_string(), LogColor::Cyan);
+                    std::thread::yield_now();
+                }
+                transaction.commit();
+                logly.info(worker, "done", LogColor::Cyan);
+            });
+        }
+    });
+
+    let mut discarded = logly.transaction();
+    discarded.warn("c", "never written", LogColor::Yellow);
+    assert_eq!(discarded.len(), 1);
+    drop(discarded);
+
+    let records = sink.records.lock().unwrap();
+    assert_eq!(records.len(), 12);
+    for worker in ["a", "b"] {
+        let start = records.iter().position(|record| record == &format!("Info {} 0 ", worker)).unwrap();
+        let block: Vec<String> = (0..5).map(|step| format!("Info {} {} ", worker, step)).collect();
+        assert_eq!(records[start..start + 5], block[..]);
+    }
+}
+
+#[test]
+pub fn test_transaction_keeps_context_of_each_message() {
+    let sink = MemorySink::default();
+
+    let mut logly = Logger::new();
+    logly.set_console_enabled(false);
+    logly.add_custom_sink(Box::new(sink.clone()));
+    logly.add_context_provider(Box::new(TraceContext));
+
+    let mut transaction = logly.transaction();
+    Logger::set_trace_context("trace-1", "span-1");
+    transaction.info("Key1", "Value1", LogColor::Cyan);
+    Logger::set_trace_context("trace-2", "span-2");
+    transaction.info("Key2", "Value2", LogColor::Cyan);
+    Logger::clear_trace_context();
+    transaction.commit();
+
+    assert_eq!(
+        *sink.records.lock().unwrap(),
+        vec![
+            "Info Key1 Value1 trace_id=trace-1,span_id=span-1".to_string(),
+            "Info Key2 Value2 trace_id=trace-2,span_id=span-2".to_string(),
+        ]
+    );
+}
+
+#[test]
+pub fn test_span_logs_duration() {
+    let sink = MemorySink::default();
+
+    let mut logly = Logger::new();
+    logly.set_console_enabled(false);
+    logly.add_custom_sink(Box::new(sink.clone()));
+
+    {
+        let _request = logly.span("request").log_start();
+        let _query = logly.span("db_query");
+        std::thread::sleep(std::time::Duration::from_millis(50));
+    }
+
+    let records = sink.records.lock().unwrap();
+    assert_eq!(records.len(), 3);
+    assert_eq!(records[0], "Debug request started ");
+    let duration_ms = |record: &str, prefix: &str| -> f64 {
+        record.strip_prefix(prefix).unwrap().parse().unwrap()
+    };
+    let query_ms = duration_ms(&records[1], "Info db_query completed duration_ms=");
+    let request_ms = duration_ms(&records[2], "Info request completed duration_ms=");
+    assert!((50.0..5000.0).contains(&query_ms), "{}", query_ms);
+    assert!(request_ms >= query_ms);
+}
+
+#[test]
+pub fn test_span_keeps_creation_context_and_bound_fields() {
+    let sink = MemorySink::default();
+
+    let mut logly = Logger::new();
+    logly.set_console_enabled(false);
+    logly.add_custom_sink(Box::new(sink.clone()));
+    logly.add_context_provider(Box::new(TraceContext));
+
+    Logger::set_trace_context("trace-1", "span-1");
+    let span = logly.child().bind("request_id", "42").span("request").log_start();
+    Logger::set_trace_context("trace-2", "span-2");
+
+    // Dropped on a thread without a trace context
+    std::thread::scope(|scope| {
+        scope.spawn(move || drop(span));
+    });
+    Logger::clear_trace_context();
+
+    let records = sink.records.lock().unwrap();
+    assert_eq!(records[0], "Debug request started request_id=42,trace_id=trace-1,span_id=span-1");
+    let completed = records[1].strip_prefix("Info request completed request_id=42,duration_ms=").unwrap();
+    assert!(completed.ends_with(",trace_id=trace-1,span_id=span-1"), "{}", completed);
+}
+
+#[test]
+pub fn test_field_filters() {
+    let sink = MemorySink::default();
+
+    let mut logly = Logger::new();
+    logly.set_console_enabled(false);
+    logly.add_custom_sink(Box::new(sink.clone()));
+    logly.add_field_filter("env", "prod");
+
+    logly.info_with("deploy", "match", &[("env", "prod")], LogColor::Cyan);
+    logly.info_with("deploy", "mismatch", &[("env", "dev")], LogColor::Cyan);
+    logly.info("deploy", "absent", LogColor::Cyan);
+
+    assert_eq!(*sink.records.lock().unwrap(), vec!["Info deploy match env=prod".to_string()]);
+    let stats = logly.stats();
+    assert_eq!(stats.total_records, 1);
+    assert_eq!(stats.filtered_records, 2);
+}
+
+#[test]
+pub fn test_global_filter_and_key_mutes() {
+    let file = TempLogFile::new("global_filter");
+    let path = file.path();
+    let first = MemorySink::default();
+    let second = MemorySink::default();
+
+    let mut logly = file_logger(path);
+    logly.set_console_enabled(false);
+    logly.add_custom_sink(Box::new(first.clone()));
+    logly.add_custom_sink(Box::new(second.clone()));
+    logly.add_key_mute("hyper");
+    logly.set_global_filter(Some(Box::new(|record: &LogRecord| record.value != "noise")));
+
+    logly.info("hyper", "muted", LogColor::Cyan);
+    logly.info("hyper::client", "muted", LogColor::Cyan);
+    logly.info("hyperlocal", "kept", LogColor::Cyan);
+    logly.info("app", "noise", LogColor::Cyan);
+    logly.info("app", "kept", LogColor::Cyan);
+    logly.stop_logging();
+
+    let expected = vec!["Info hyperlocal kept ".to_string(), "Info app kept ".to_string()];
+    assert_eq!(*first.records.lock().unwrap(), expected);
+    assert_eq!(*second.records.lock().unwrap(), expected);
+    let content = file.read();
+    assert_eq!(content, "[Info]: hyperlocal - kept\n[Info]: app - kept\n");
+    assert_eq!(logly.stats().filtered_records, 3);
+    assert!(!logly.enabled("hyper::client", LogLevel::Error));
+    assert!(logly.enabled("hyperlocal", LogLevel::Trace));
+}
+
+// Context provider counting how many records it enriched
+struct CountingProvider(Arc<AtomicUsize>);
+
+impl ContextProvider for CountingProvider {
+    fn enrich(&self, _fields: &mut Vec<(String, String)>) {
+        self.0.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+#[test]
+pub fn test_muted_and_directive_filtered_records_are_not_built() {
+    let built = Arc::new(AtomicUsize::new(0));
+
+    let mut logly = Logger::new();
+    logly.set_console_enabled(false);
+    logly.add_context_provider(Box::new(CountingProvider(Arc::clone(&built))));
+    logly.add_key_mute("hyper");
+    logly.set_filter_directives("info").unwrap();
+
+    logly.info("hyper::client", "muted", LogColor::Cyan);
+    logly.debug("app", "below the default level", LogColor::Blue);
+    assert_eq!(built.load(Ordering::SeqCst), 0);
+    assert_eq!(logly.stats().filtered_records, 2);
+
+    logly.info("app", "kept", LogColor::Cyan);
+    assert_eq!(built.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+pub fn test_filter_directives() {
+    let sink = MemorySink::default();
+
+    let mut logly = Logger::new();
+    logly.set_console_enabled(false);
+    logly.add_custom_sink(Box::new(sink.clone()));
+    logly.set_filter_directives("warn, myapp=info, myapp::db=debug").unwrap();
+    assert!(logly.enabled("myapp::db::pool", LogLevel::Debug));
+    assert!(!logly.enabled("myapp::web", LogLevel::Debug));
+    assert!(!logly.enabled("hyper", LogLevel::Info));
+
+    logly.debug("myapp::db::pool", "kept", LogColor::Blue);
+    logly.trace("myapp::db", "dropped", LogColor::Blue);
+    logly.debug("myapp::web", "dropped", LogColor::Blue);
+    logly.info("myapp::web", "kept", LogColor::Cyan);
+    logly.info("hyper", "dropped", LogColor::Cyan);
+    logly.warn("hyper", "kept", LogColor::Yellow);
+
+    assert_eq!(
+        *sink.records.lock().unwrap(),
+        vec![
+            "Debug myapp::db::pool kept ".to_string(),
+            "Info myapp::web kept ".to_string(),
+            "Warn hyper kept ".to_string(),
+        ]
+    );
+
+    // Without a default level, keys matching no module pass
+    logly.set_filter_directives("hyper=off").unwrap();
+    logly.error("hyper::client", "dropped", LogColor::Red);
+    logly.trace("app", "kept", LogColor::Blue);
+    assert_eq!(sink.records.lock().unwrap().last().unwrap(), "Trace app kept ");
+    assert_eq!(logly.stats().filtered_records, 4);
+
+    assert!(logly.set_filter_directives("myapp=loud").is_err());
+}
+
+#[test]
+pub fn test_filter_directives_last_duplicate_wins() {
+    let mut logly = Logger::new();
+
+    logly.set_filter_directives("a=info, b, a=error, b=off, debug, warn").unwrap();
+    assert!(!logly.enabled("a", LogLevel::Warn));
+    assert!(logly.enabled("a::db", LogLevel::Error));
+    assert!(!logly.enabled("b", LogLevel::Fatal));
+    assert!(!logly.enabled("c", LogLevel::Info));
+    assert!(logly.enabled("c", LogLevel::Warn));
+}
+
+// Formatter writing only the message, uppercased
+struct ShoutingFormatter;
+
+impl RecordFormatter for ShoutingFormatter {
+    fn format(&self, record: &LogRecord) -> String {
+        record.value.to_uppercase()
+    }
+}
+
+// Formatter counting how often it runs
+struct CountingFormatter(Arc<AtomicUsize>);
+
+impl RecordFormatter for CountingFormatter {
+    fn format(&self, record: &LogRecord) -> String {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        record.value.to_string()
+    }
+}
+
+#[test]
+pub fn test_custom_formatter_runs_once_per_record() {
+    let file = TempLogFile::new("formatter_once");
+    let calls = Arc::new(AtomicUsize::new(0));
+
+    let mut logly = file_logger(file.path());
+    logly.set_console_target(ConsoleTarget::Stderr);
+    logly.set_console_line_ending(LineEnding::CrLf);
+    logly.set_formatter(Some(Box::new(CountingFormatter(Arc::clone(&calls)))));
+
+    logly.info("Key1", "formatted once", LogColor::Cyan);
+    logly.stop_logging();
+
+    // The console gets the same string with its own line ending
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+    assert_eq!(file.read(), "formatted once\n");
+}
+
+#[test]
+pub fn test_custom_formatter() {
+    let file = TempLogFile::new("formatter");
+    let path = file.path();
+
+    let mut logly = Logger::builder().file_sink(path).build().unwrap();
+    logly.set_formatter(Some(Box::new(ShoutingFormatter)));
+
+    assert_eq!(logly.format_record(LogLevel::Info, "Key1", "quiet please", LogColor::Cyan), "QUIET PLEASE\n");
+    logly.info("Key1", "hello", LogColor::Cyan);
+
+    logly.set_formatter(None);
+    logly.info("Key2", "back to normal", LogColor::Cyan);
+    logly.stop_logging();
+
+    let content = file.read();
+    assert_eq!(content, "HELLO\n\x1b[36m[Info]: Key2 - back to normal\x1b[0m\x1b[0m\n");
+}
+
+#[test]
+pub fn test_level_override() {
+    let file = TempLogFile::new("level_override");
+    let path = file.path();
+    let sink = MemorySink::default();
+
+    let mut logly = file_logger(path);
+    logly.set_console_enabled(false);
+    logly.set_storage_level(LogLevel::Info, false);
+    logly.add_custom_sink(Box::new(sink.clone()));
+    logly.set_level_override(Some(Box::new(|record: &LogRecord| {
+        record.value.contains("panic").then_some(LogLevel::Critical)
+    })));
+
+    logly.info("worker", "thread panicked", LogColor::Cyan);
+    logly.info("worker", "all good", LogColor::Cyan);
+    logly.stop_logging();
+
+    let content = file.read();
+    assert_eq!(content, "[Critical]: worker - thread panicked\n");
+    assert_eq!(
+        *sink.records.lock().unwrap(),
+        vec!["Critical worker thread panicked ".to_string(), "Info worker all good ".to_string()]
+    );
+    assert_eq!(logly.stats().records(LogLevel::Critical), 1);
+    assert_eq!(logly.stats().records(LogLevel::Info), 1);
+}
+
+#[test]
+pub fn test_filter_directives_use_the_overridden_level() {
+    let sink = MemorySink::default();
+
+    let mut logly = Logger::new();
+    logly.set_console_enabled(false);
+    logly.add_custom_sink(Box::new(sink.clone()));
+    logly.set_filter_directives("warn").unwrap();
+    logly.set_level_override(Some(Box::new(|record: &LogRecord| match record.value.as_ref() {
+        "thread panicked" => Some(LogLevel::Critical),
+        "demoted" => Some(LogLevel::Debug),
+        _ => None,
+    })));
+
+    // Below the directive before the override, so it cannot be ruled out up front
+    assert!(logly.enabled("worker", LogLevel::Info));
+    logly.info("worker", "thread panicked", LogColor::Cyan);
+    logly.info("worker", "all good", LogColor::Cyan);
+    logly.error("worker", "demoted", LogColor::Red);
+
+    assert_eq!(*sink.records.lock().unwrap(), vec!["Critical worker thread panicked ".to_string()]);
+    assert_eq!(logly.stats().filtered_records, 2);
+}
+
+#[derive(Debug)]
+struct ChainError {
+    message: &'static str,
+    source: Option<Box<ChainError>>,
+}
+
+impl std::fmt::Display for ChainError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for ChainError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.source.as_deref().map(|source| source as &(dyn std::error::Error + 'static))
+    }
+}
+
+#[test]
+pub fn test_error_err_records_source_chain() {
+    let sink = MemorySink::default();
+    let mut logly = Logger::new();
+    logly.set_console_enabled(false);
+    logly.add_custom_sink(Box::new(sink.clone()));
+
+    let err = ChainError {
+        message: "could not load config",
+        source: Some(Box::new(ChainError {
+            message: "could not read file",
+            source: Some(Box::new(ChainError { message: "permission denied", source: None })),
+        })),
+    };
+    logly.error_err("startup", &err);
+
+    assert_eq!(
+        *sink.records.lock().unwrap(),
+        vec![
+            "Error startup could not load config error=could not load config,error.source.0=could not read file,error.source.1=permission denied"
+                .to_string()
+        ]
+    );
+}
+
+#[test]
+pub fn test_sink_logging_to_its_own_logger_does_not_deadlock() {
+    let file = TempLogFile::new("reentrant_sink");
+    let handle = Arc::new(OnceLock::new());
+    let writes = Arc::new(AtomicUsize::new(0));
+
+    let mut logly = file_logger(file.path());
+    logly.set_console_enabled(false);
+    logly.add_custom_sink(Box::new(ReentrantSink {
+        logger: Arc::clone(&handle),
+        writes: Arc::clone(&writes),
+    }));
+    let logly: &'static Logger = Box::leak(Box::new(logly));
+    assert!(handle.set(logly).is_ok());
+
+    logly.info("Key1", "Value1", LogColor::Cyan);
+    logly.stop_logging();
+
+    // The nested message went to stderr, not back through the sink or into the file
+    assert_eq!(writes.load(Ordering::SeqCst), 1);
+    assert_eq!(file.read(), "[Info]: Key1 - Value1\n");
+}
+
+#[test]
+pub fn test_formatter_may_log_through_its_own_logger() {
+    let file = TempLogFile::new("reentrant_formatter");
+    let handle = Arc::new(OnceLock::new());
+
+    let mut logly = file_logger(file.path());
+    logly.set_console_enabled(false);
+    logly.set_formatter(Some(Box::new(ReentrantFormatter { logger: Arc::clone(&handle) })));
+    let logly: &'static Logger = Box::leak(Box::new(logly));
+    assert!(handle.set(logly).is_ok());
+
+    logly.info("Key1", "Value1", LogColor::Cyan);
+    logly.stop_logging();
+
+    assert_eq!(file.read(), "formatter formatting\nKey1 Value1\n");
+}
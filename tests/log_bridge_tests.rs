@@ -0,0 +1,34 @@
+// logly/tests/log_bridge_tests.rs
+
+mod common;
+
+use common::{file_logger, TempLogFile};
+
+#[test]
+pub fn test_log_macros_reach_logly() {
+    let file = TempLogFile::new("log_bridge");
+    let path = file.path();
+
+    let mut logly = file_logger(path);
+    logly.set_console_enabled(false);
+    logly.set_filter_directives("info, noisy=off").unwrap();
+    // The bridge shares the global logger
+    assert!(logly::set_global(logly).is_ok());
+    logly::init_log_bridge(logly::global().unwrap()).expect("Error installing log bridge");
+
+    assert_eq!(log::max_level(), log::LevelFilter::Info);
+    assert!(!log::log_enabled!(target: "noisy", log::Level::Error));
+    assert!(log::log_enabled!(target: "dependency", log::Level::Info));
+
+    log::info!(target: "dependency", "connected to {}", "db");
+    log::debug!(target: "dependency", "handshake done");
+    log::error!(target: "noisy", "ignored");
+    logly::warn!("app", "retrying").unwrap();
+    log::error!(target: "dependency", "lost connection");
+
+    let content = file.read();
+    assert_eq!(
+        content,
+        "[Info]: dependency - connected to db\n[Warn]: app - retrying\n[Error]: dependency - lost connection\n"
+    );
+}
@@ -0,0 +1,74 @@
+// logly/tests/panic_hook_tests.rs
+
+mod common;
+
+use common::MemorySink;
+use logly::logly::*;
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+// The panic hook is process-wide, so the tests installing it take turns
+static HOOK_LOCK: Mutex<()> = Mutex::new(());
+
+// Custom sink panicking on its first write
+#[derive(Default)]
+struct PanickingSink {
+    panicked: AtomicBool,
+}
+
+impl LogSink for PanickingSink {
+    fn write(&self, _record: &LogRecord) -> std::io::Result<()> {
+        if !self.panicked.swap(true, Ordering::SeqCst) {
+            panic!("sink exploded");
+        }
+        Ok(())
+    }
+}
+
+#[test]
+pub fn test_panic_hook_logs_critical_record() {
+    let _hook = HOOK_LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let sink = MemorySink::default();
+    let mut logly = Logger::new();
+    logly.set_console_enabled(false);
+    logly.add_custom_sink(Box::new(sink.clone()));
+
+    let logly: &'static Logger = Box::leak(Box::new(logly));
+    logly.install_panic_hook(false);
+
+    let line = line!() + 1;
+    let result = std::panic::catch_unwind(|| panic!("worker {} failed", 3));
+    let _ = std::panic::take_hook();
+
+    assert!(result.is_err());
+    let records = sink.records.lock().unwrap();
+    assert_eq!(records.len(), 1);
+    assert!(records[0].starts_with(&format!(
+        "Critical panic worker 3 failed location=tests/panic_hook_tests.rs:{}:",
+        line
+    )));
+    assert!(records[0].ends_with(",thread=test_panic_hook_logs_critical_record"));
+}
+
+#[test]
+pub fn test_panic_inside_a_sink_does_not_deadlock_the_hook() {
+    let _hook = HOOK_LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let sink = MemorySink::default();
+    let mut logly = Logger::new();
+    logly.set_console_enabled(false);
+    logly.add_custom_sink(Box::new(PanickingSink::default()));
+    logly.add_custom_sink(Box::new(sink.clone()));
+
+    let logly: &'static Logger = Box::leak(Box::new(logly));
+    logly.install_panic_hook(false);
+
+    // The hook runs while this thread holds the write lock; its record goes to stderr instead
+    let result = std::panic::catch_unwind(AssertUnwindSafe(|| logly.info("Key1", "Value1", LogColor::Cyan)));
+    let _ = std::panic::take_hook();
+    assert!(result.is_err());
+
+    // Unwinding released the write lock
+    logly.info("Key2", "Value2", LogColor::Cyan);
+    assert_eq!(*sink.records.lock().unwrap(), vec!["Info Key2 Value2 ".to_string()]);
+}
@@ -0,0 +1,45 @@
+// logly/tests/shutdown_tests.rs
+
+mod common;
+
+use common::{file_logger, TempLogFile};
+use logly::logly::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+struct FlushCounter(Arc<AtomicUsize>);
+
+impl LogSink for FlushCounter {
+    fn write(&self, _record: &LogRecord) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    fn flush(&self) -> std::io::Result<()> {
+        self.0.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+#[test]
+pub fn test_shutdown_flushes_global_logger() {
+    assert!(logly::shutdown().is_ok());
+
+    let file = TempLogFile::new("shutdown");
+    let path = file.path();
+    let flushes = Arc::new(AtomicUsize::new(0));
+
+    let mut logly = file_logger(path);
+    logly.set_console_enabled(false);
+    logly.add_custom_sink(Box::new(FlushCounter(Arc::clone(&flushes))));
+    logly::set_global(logly).unwrap();
+
+    logly::info!("Key1", "Value1").unwrap();
+    logly::error!("Key2", "Value2").unwrap();
+
+    assert!(logly::shutdown().is_ok());
+    assert!(logly::shutdown().is_ok());
+    assert_eq!(flushes.load(Ordering::Relaxed), 2);
+
+    let content = file.read();
+    assert_eq!(content, "[Info]: Key1 - Value1\n[Error]: Key2 - Value2\n");
+}